@@ -0,0 +1,724 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use megafon_cfg_viewer::callgraph::CallGraph;
+use megafon_cfg_viewer::charset::{self, SubstitutionPolicy, TextEncoding};
+use megafon_cfg_viewer::config::{self, ParseOptions, ScreenConfig, Severity};
+use megafon_cfg_viewer::diff;
+use megafon_cfg_viewer::directory::{Directory, DirectoryLabelRule};
+use megafon_cfg_viewer::fleet::Fleet;
+use megafon_cfg_viewer::formats::{self, StructuredFormat};
+use megafon_cfg_viewer::merge;
+use megafon_cfg_viewer::rules::{self, LintConfig, Linter};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+// -----------------------------------------------------------------------------
+// command line
+// -----------------------------------------------------------------------------
+
+/// Консольный инструмент для конфигураций сенсорной панели ОРМ СКРС "Мегафон".
+#[derive(Parser)]
+#[command(name = "megafon-cfg", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the parsed config.
+    Dump {
+        file: PathBuf,
+        /// Input format, detected from the extension by default.
+        #[arg(long)]
+        from: Option<Format>,
+        /// Output format; `text` is a human-readable listing.
+        #[arg(long, short, default_value = "text")]
+        format: DumpFormat,
+        /// Subscriber directory (CSV) to annotate phone buttons in the
+        /// text listing.
+        #[arg(long, value_name = "CSV")]
+        directory: Option<PathBuf>,
+    },
+    /// Check configs and button layout; exit with a non-zero code on errors.
+    Validate {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Treat unknown keys, unexpected sections and missing fields as errors.
+        #[arg(long)]
+        strict: bool,
+        /// Fail on warnings too.
+        #[arg(long)]
+        deny_warnings: bool,
+        /// Lint rule settings; `megafon-lint.toml` in the current directory
+        /// is used if present.
+        #[arg(long)]
+        rules: Option<PathBuf>,
+        /// Subscriber directory (CSV); enables the `phone-directory-label`
+        /// check of button labels.
+        #[arg(long, value_name = "CSV")]
+        directory: Option<PathBuf>,
+    },
+    /// List lint rules with their IDs and default severities.
+    Rules,
+    /// Convert a config between formats and encodings.
+    Convert {
+        input: PathBuf,
+        /// Output file, `-` for stdout.
+        #[arg(long, short)]
+        output: PathBuf,
+        #[arg(long)]
+        from: Option<Format>,
+        #[arg(long)]
+        to: Option<Format>,
+        /// Encoding of a `.conf` output: koi8-r, cp1251, utf-8 or utf-8-bom.
+        /// Defaults to the input encoding, or KOI8-R for structured input.
+        #[arg(long)]
+        encoding: Option<TextEncoding>,
+        /// Replace characters missing from the target encoding with look-alikes.
+        #[arg(long)]
+        substitute: bool,
+    },
+    /// Show structural differences between two configs; exits with 1 if
+    /// they differ.
+    Diff { old: PathBuf, new: PathBuf },
+    /// Three-way merge of .conf files; usable as a git merge driver
+    /// (`megafon-cfg merge %O %A %B`). On conflicts nothing is written, the
+    /// conflicting values are listed on stderr and the exit code is 1.
+    Merge {
+        base: PathBuf,
+        ours: PathBuf,
+        theirs: PathBuf,
+        /// Output file; defaults to overwriting OURS, as git expects.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Site-wide call graph of a directory of configs.
+    Calls {
+        dir: PathBuf,
+        /// Print the graph in Graphviz DOT format.
+        #[arg(long)]
+        dot: bool,
+        /// List workstations that cannot reach ADDRESS.
+        #[arg(long, value_name = "ADDRESS")]
+        unreachable: Option<u32>,
+        /// Print the shortest call chain between two addresses.
+        #[arg(long, num_args = 2, value_names = ["FROM", "TO"])]
+        path: Option<Vec<u32>>,
+    },
+    /// Report buttons dialing unknown addresses and workstations nobody
+    /// can call; exits with 1 if anything is found.
+    Dangling {
+        dir: PathBuf,
+        /// Addresses of subscribers outside the loaded configs.
+        #[arg(long, value_name = "ADDRESS", num_args = 1..)]
+        known: Vec<u32>,
+        /// Subscriber directory (CSV); its addresses count as known.
+        #[arg(long, value_name = "CSV")]
+        directory: Option<PathBuf>,
+    },
+    /// Move every phone button dialing FROM to TO across a directory of
+    /// configs. Only the changed files are rewritten.
+    ReplaceAddress {
+        dir: PathBuf,
+        from: u32,
+        to: u32,
+        /// New label for the changed buttons.
+        #[arg(long)]
+        label: Option<String>,
+        /// Show the changes per file without saving them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Report addresses labelled differently on different workstations;
+    /// exits with 1 if any are found.
+    Labels {
+        dir: PathBuf,
+        /// Relabel every button dialing ADDRESS with the variant number
+        /// VARIANT from the report.
+        #[arg(long, num_args = 2, value_names = ["ADDRESS", "VARIANT"])]
+        apply: Option<Vec<u32>>,
+        /// With --apply, show the changes per file without saving them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print summary counts.
+    Info {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Conf,
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    fn detect(path: &Path, explicit: Option<Format>) -> Format {
+        explicit.unwrap_or_else(|| match StructuredFormat::from_path(path) {
+            Some(StructuredFormat::Json) => Format::Json,
+            Some(StructuredFormat::Yaml) => Format::Yaml,
+            Some(StructuredFormat::Toml) => Format::Toml,
+            None => Format::Conf,
+        })
+    }
+
+    fn structured(self) -> Option<StructuredFormat> {
+        match self {
+            Format::Conf => None,
+            Format::Json => Some(StructuredFormat::Json),
+            Format::Yaml => Some(StructuredFormat::Yaml),
+            Format::Toml => Some(StructuredFormat::Toml),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DumpFormat {
+    Text,
+    Conf,
+    Json,
+    Yaml,
+    Toml,
+}
+
+// -----------------------------------------------------------------------------
+// input
+// -----------------------------------------------------------------------------
+
+/// Прочитанный файл: для `.conf` помимо конфигурации хранится исходный текст
+/// и его кодировка.
+struct Input {
+    config: ScreenConfig,
+    source: Option<(String, TextEncoding)>,
+}
+
+fn read_input(path: &Path, from: Option<Format>) -> Result<Input, String> {
+    let bytes = std::fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let fail = |err: &dyn std::fmt::Display| format!("{}: {}", path.display(), err);
+    match Format::detect(path, from).structured() {
+        Some(format) => {
+            let text = String::from_utf8(bytes).map_err(|err| fail(&err))?;
+            let config = formats::import(&text, format).map_err(|err| fail(&err))?;
+            Ok(Input {
+                config,
+                source: None,
+            })
+        }
+        None => {
+            let decoded = charset::decode_auto(&bytes).map_err(|err| fail(&err))?;
+            let config = config::parse(&decoded.text).map_err(|err| fail(&err))?;
+            Ok(Input {
+                config,
+                source: Some((decoded.text, decoded.encoding)),
+            })
+        }
+    }
+}
+
+fn load_directory(path: Option<&Path>) -> Result<Option<Directory>, String> {
+    path.map(|path| {
+        Directory::load_path(path).map_err(|err| format!("{}: {}", path.display(), err))
+    })
+    .transpose()
+}
+
+fn write_output(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if path.as_os_str() == "-" {
+        use std::io::Write;
+        std::io::stdout()
+            .write_all(bytes)
+            .map_err(|err| err.to_string())
+    } else {
+        std::fs::write(path, bytes).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// commands
+// -----------------------------------------------------------------------------
+
+fn dump(
+    file: &Path,
+    from: Option<Format>,
+    format: DumpFormat,
+    directory: Option<&Path>,
+) -> Result<(), String> {
+    let config = read_input(file, from)?.config;
+    let directory = load_directory(directory)?;
+    let structured = match format {
+        DumpFormat::Text => {
+            let text = listing(&config, directory.as_ref());
+            return write_output(Path::new("-"), text.as_bytes());
+        }
+        DumpFormat::Conf => {
+            return write_output(Path::new("-"), config::serialize(&config).as_bytes());
+        }
+        DumpFormat::Json => StructuredFormat::Json,
+        DumpFormat::Yaml => StructuredFormat::Yaml,
+        DumpFormat::Toml => StructuredFormat::Toml,
+    };
+    let text = formats::export(&config, structured).map_err(|err| err.to_string())?;
+    write_output(Path::new("-"), text.as_bytes())
+}
+
+fn listing(config: &ScreenConfig, directory: Option<&Directory>) -> String {
+    let mut out = String::new();
+    let name = config.name.as_deref().unwrap_or("-");
+    let address = config
+        .internal_address
+        .map_or("-".to_string(), |a| a.to_string());
+    out += &format!("{} (internal address {})\n", name, address);
+
+    out += "Available radiostations:\n";
+    for station in &config.available_radiostations {
+        out += &format!("  slot {:>3}  {}\n", station.slot, station.radio_name);
+    }
+    for panel in &config.phone_panels {
+        out += &format!("Phone panel {}:\n", panel.id);
+        for button in &panel.buttons {
+            out += &format!(
+                "  {}  {:>5}  {:<32} x={:.3} y={:.3} w={:.3} h={:.3}\n",
+                button.id,
+                button.internal_address,
+                button.text,
+                button.position_x,
+                button.position_y,
+                button.size_width,
+                button.size_height
+            );
+            if let Some(subscriber) = directory.and_then(|d| d.get(button.internal_address)) {
+                out += &format!("           -> {}\n", subscriber);
+            }
+        }
+    }
+    for panel in &config.radio_panels {
+        out += &format!("Radio panel {}:\n", panel.id);
+        for button in &panel.buttons {
+            out += &format!(
+                "  {}  slot {:>3}  {:<27} x={:.3} y={:.3} w={:.3} h={:.3}\n",
+                button.id,
+                button.slot,
+                button.text,
+                button.position_x,
+                button.position_y,
+                button.size_width,
+                button.size_height
+            );
+        }
+    }
+    out
+}
+
+/// Проверяет файлы и возвращает `true`, если ни в одном нет ошибок.
+fn validate(files: &[PathBuf], strict: bool, deny_warnings: bool, linter: &Linter) -> bool {
+    let options = ParseOptions {
+        recover: true,
+        strict,
+    };
+    let mut ok = true;
+    for file in files {
+        let bytes = match std::fs::read(file) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                eprintln!("{}: {}", file.display(), err);
+                ok = false;
+                continue;
+            }
+        };
+        let decoded = charset::decode_auto_lossy(&bytes);
+        for offset in &decoded.invalid_offsets {
+            eprintln!(
+                "{}: error: byte at offset {} is not valid {}",
+                file.display(),
+                offset,
+                decoded.encoding
+            );
+            ok = false;
+        }
+        let report = match config::parse_with_options(&decoded.text, options) {
+            Ok(report) => report,
+            Err(err) => {
+                eprintln!("{}: error: {}", file.display(), err);
+                ok = false;
+                continue;
+            }
+        };
+        for diagnostic in &report.diagnostics {
+            eprintln!("{}: {}", file.display(), diagnostic);
+            if diagnostic.severity == Severity::Error || deny_warnings {
+                ok = false;
+            }
+        }
+        for message in linter.run(&report.config) {
+            eprintln!("{}: {}", file.display(), message);
+            if message.severity == Severity::Error || deny_warnings {
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// Загружает настройки правил из указанного файла или из файла проекта в
+/// текущем каталоге. Если задан справочник абонентов, подключает правило
+/// `phone-directory-label`.
+fn load_linter(path: Option<&Path>, directory: Option<Directory>) -> Result<Linter, String> {
+    let default_path = Path::new(rules::PROJECT_FILE);
+    let path = match path {
+        Some(path) => Some(path),
+        None if default_path.exists() => Some(default_path),
+        None => None,
+    };
+    let config = match path {
+        Some(path) => {
+            LintConfig::load(path).map_err(|err| format!("{}: {}", path.display(), err))?
+        }
+        None => LintConfig::default(),
+    };
+    let mut linter = Linter::new(config);
+    if let Some(directory) = directory {
+        linter.add_rule(Box::new(DirectoryLabelRule { directory }));
+    }
+    let Some(path) = path else {
+        return Ok(linter);
+    };
+    for id in linter.unknown_rule_ids() {
+        eprintln!("{}: warning: unknown rule {:?}", path.display(), id);
+    }
+    Ok(linter)
+}
+
+fn list_rules() -> Result<(), String> {
+    // The directory rule only runs with `--directory`, but it is configured
+    // in the project file like the others.
+    let mut linter = Linter::default();
+    linter.add_rule(Box::new(DirectoryLabelRule {
+        directory: Directory::default(),
+    }));
+    for rule in linter.rules() {
+        let severity = match rule.default_severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warn",
+        };
+        println!("{:<26} {:<6} {}", rule.id(), severity, rule.description());
+    }
+    Ok(())
+}
+
+fn convert(
+    input: &Path,
+    output: &Path,
+    from: Option<Format>,
+    to: Option<Format>,
+    encoding: Option<TextEncoding>,
+    substitute: bool,
+) -> Result<(), String> {
+    let Input { config, source } = read_input(input, from)?;
+    let bytes = match Format::detect(output, to).structured() {
+        Some(format) => formats::export(&config, format)
+            .map_err(|err| err.to_string())?
+            .into_bytes(),
+        None => {
+            // Re-encoding a .conf keeps its text as is; otherwise write it out anew.
+            let (text, source_encoding) = match source {
+                Some((text, encoding)) => (text, encoding),
+                None => (config::serialize(&config), TextEncoding::Koi8R),
+            };
+            let policy = if substitute {
+                SubstitutionPolicy::Substitute
+            } else {
+                SubstitutionPolicy::Strict
+            };
+            let encoded = config::encode_config(&text, encoding.unwrap_or(source_encoding), policy)
+                .map_err(|err| match err {
+                    config::EncodeError::Unrepresentable { ref issues, .. } => {
+                        let mut message = err.to_string();
+                        for issue in issues {
+                            message += &format!("\n  {}", issue);
+                        }
+                        message
+                    }
+                })?;
+            for issue in &encoded.substitutions {
+                eprintln!("warning: {}", issue);
+            }
+            encoded.bytes
+        }
+    };
+    write_output(output, &bytes)
+}
+
+/// Печатает сводку по каждому файлу и возвращает `true`, если прочитать
+/// удалось все. Файл с ошибкой не мешает сводке по остальным.
+fn info(files: &[PathBuf]) -> bool {
+    let mut ok = true;
+    for file in files {
+        let Input { config, source } = match read_input(file, None) {
+            Ok(input) => input,
+            Err(message) => {
+                eprintln!("error: {}", message);
+                ok = false;
+                continue;
+            }
+        };
+        let phone_buttons: usize = config.phone_panels.iter().map(|p| p.buttons.len()).sum();
+        let radio_buttons: usize = config.radio_panels.iter().map(|p| p.buttons.len()).sum();
+        println!("{}", file.display());
+        if let Some((_, encoding)) = source {
+            println!("  encoding:         {}", encoding);
+        }
+        println!(
+            "  name:             {}",
+            config.name.as_deref().unwrap_or("-")
+        );
+        println!(
+            "  internal address: {}",
+            config
+                .internal_address
+                .map_or("-".to_string(), |a| a.to_string())
+        );
+        println!(
+            "  radiostations:    {}",
+            config.available_radiostations.len()
+        );
+        println!("  phone panels:     {}", config.phone_panels.len());
+        println!("  phone buttons:    {}", phone_buttons);
+        println!("  radio panels:     {}", config.radio_panels.len());
+        println!("  radio buttons:    {}", radio_buttons);
+    }
+    ok
+}
+
+fn load_fleet(dir: &Path) -> Result<Fleet, String> {
+    let fleet = Fleet::load(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    for error in &fleet.errors {
+        eprintln!("warning: {}", error);
+    }
+    Ok(fleet)
+}
+
+fn calls(
+    dir: &Path,
+    dot: bool,
+    unreachable: Option<u32>,
+    path: Option<&[u32]>,
+) -> Result<(), String> {
+    let graph = CallGraph::from_fleet(&load_fleet(dir)?);
+    let describe = |address: u32| match graph.subscriber(address).and_then(|s| s.name.as_deref()) {
+        Some(name) => format!("{} {}", address, name),
+        None => address.to_string(),
+    };
+
+    if dot {
+        print!("{}", graph.to_dot());
+    } else if let Some(target) = unreachable {
+        for address in graph.cannot_reach(target) {
+            println!("{}", describe(address));
+        }
+    } else if let Some(&[from, to]) = path {
+        match graph.path(from, to) {
+            Some(chain) => {
+                let chain: Vec<String> = chain.into_iter().map(describe).collect();
+                println!("{}", chain.join(" -> "));
+            }
+            None => return Err(format!("{} cannot reach {}", from, to)),
+        }
+    } else {
+        for from in graph.workstations() {
+            println!("{}", describe(from));
+            for to in graph.callees(from) {
+                println!("  -> {}", describe(to));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Печатает отчёт о висящих адресах и возвращает `true`, если он пуст.
+fn dangling(dir: &Path, known: &[u32], directory: Option<&Path>) -> Result<bool, String> {
+    let fleet = load_fleet(dir)?;
+    let mut known = known.to_vec();
+    if let Some(directory) = load_directory(directory)? {
+        known.extend(directory.addresses());
+    }
+    let report = fleet.dangling_report(&known);
+    print!("{}", report);
+    Ok(report.is_empty())
+}
+
+fn replace_address(
+    dir: &Path,
+    from: u32,
+    to: u32,
+    label: Option<&str>,
+    dry_run: bool,
+) -> Result<(), String> {
+    let fleet = load_fleet(dir)?;
+    let edit = fleet.replace_address(from, to, label);
+    print!("{}", edit);
+    if edit.is_empty() {
+        println!("no buttons dial {}", from);
+    } else if !dry_run {
+        edit.save().map_err(|err| err.to_string())?;
+        println!(
+            "updated {} button(s) in {} file(s)",
+            edit.button_count(),
+            edit.files.len()
+        );
+    }
+    Ok(())
+}
+
+/// Печатает разночтения в подписях и возвращает `true`, если их нет. С
+/// `apply` вместо отчёта применяет выбранную подпись.
+fn labels(dir: &Path, apply: Option<&[u32]>, dry_run: bool) -> Result<bool, String> {
+    let fleet = load_fleet(dir)?;
+    let report = fleet.label_report();
+    let Some(&[address, variant]) = apply else {
+        print!("{}", report);
+        return Ok(report.inconsistent().next().is_none());
+    };
+    let labels = report
+        .get(address)
+        .ok_or_else(|| format!("no buttons dial {}", address))?;
+    let label = (variant as usize)
+        .checked_sub(1)
+        .and_then(|idx| labels.variants.get(idx))
+        .ok_or_else(|| format!("{} has no label variant {}", address, variant))?;
+    let edit = fleet.apply_label(address, &label.text);
+    print!("{}", edit);
+    if !dry_run && !edit.is_empty() {
+        edit.save().map_err(|err| err.to_string())?;
+        println!(
+            "relabelled {} button(s) in {} file(s)",
+            edit.button_count(),
+            edit.files.len()
+        );
+    }
+    Ok(true)
+}
+
+/// Печатает различия и возвращает `true`, если конфигурации совпадают.
+fn show_diff(old: &Path, new: &Path) -> Result<bool, String> {
+    let old = read_input(old, None)?.config;
+    let new = read_input(new, None)?.config;
+    let changes = diff::diff(&old, &new);
+    print!("{}", changes);
+    Ok(changes.is_empty())
+}
+
+/// Сливает три версии и записывает результат поверх текста нашей версии,
+/// сохраняя её форматирование и кодировку. Возвращает `true`, если
+/// конфликтов нет.
+///
+/// При конфликтах результат не записывается: в нём спорные места взяты из
+/// нашей версии, и правки другой стороны пропали бы без следа. Git в этом
+/// случае оставляет файл как есть и помечает его конфликтующим, а список
+/// конфликтов со значениями всех трёх версий печатается в stderr.
+fn merge_files(base: &Path, ours: &Path, theirs: &Path, output: &Path) -> Result<bool, String> {
+    let base_cfg = read_input(base, Some(Format::Conf))?.config;
+    let theirs_cfg = read_input(theirs, Some(Format::Conf))?.config;
+    let Input {
+        config: ours_cfg,
+        source,
+    } = read_input(ours, Some(Format::Conf))?;
+    let (text, encoding) = source.unwrap_or_else(|| (String::new(), TextEncoding::Koi8R));
+
+    let result = merge::merge(&base_cfg, &ours_cfg, &theirs_cfg);
+    if !result.is_clean() {
+        for conflict in &result.conflicts {
+            eprintln!("conflict: {}", conflict);
+        }
+        return Ok(false);
+    }
+
+    let mut doc = config::parse_document(&text);
+    config::update_document(&mut doc, &result.config);
+    let encoded = config::encode_config(&doc.to_string(), encoding, SubstitutionPolicy::Strict)
+        .map_err(|err| format!("{}: {}", output.display(), err))?;
+    write_output(output, &encoded.bytes)?;
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Dump {
+            file,
+            from,
+            format,
+            directory,
+        } => dump(&file, from, format, directory.as_deref()).map(|()| true),
+        Command::Validate {
+            files,
+            strict,
+            deny_warnings,
+            rules,
+            directory,
+        } => load_directory(directory.as_deref())
+            .and_then(|directory| load_linter(rules.as_deref(), directory))
+            .map(|linter| validate(&files, strict, deny_warnings, &linter)),
+        Command::Rules => list_rules().map(|()| true),
+        Command::Convert {
+            input,
+            output,
+            from,
+            to,
+            encoding,
+            substitute,
+        } => convert(&input, &output, from, to, encoding, substitute).map(|()| true),
+        Command::Diff { old, new } => show_diff(&old, &new),
+        Command::Merge {
+            base,
+            ours,
+            theirs,
+            output,
+        } => {
+            let output = output.unwrap_or_else(|| ours.clone());
+            merge_files(&base, &ours, &theirs, &output)
+        }
+        Command::Calls {
+            dir,
+            dot,
+            unreachable,
+            path,
+        } => calls(&dir, dot, unreachable, path.as_deref()).map(|()| true),
+        Command::Dangling {
+            dir,
+            known,
+            directory,
+        } => dangling(&dir, &known, directory.as_deref()),
+        Command::ReplaceAddress {
+            dir,
+            from,
+            to,
+            label,
+            dry_run,
+        } => replace_address(&dir, from, to, label.as_deref(), dry_run).map(|()| true),
+        Command::Labels {
+            dir,
+            apply,
+            dry_run,
+        } => labels(&dir, apply.as_deref(), dry_run),
+        Command::Info { files } => Ok(info(&files)),
+    };
+    exit_status(result)
+}
+
+/// Код завершения команды: 0 - успех, 1 - команда отработала, но нашла
+/// проблемы (ошибки проверки, различия, конфликты), 2 - команду не удалось
+/// выполнить.
+fn exit_status(result: Result<bool, String>) -> ExitCode {
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,601 @@
+use crate::generic::{Document, Entry, LineScanner, LineWriter, Location, Node, Section};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::ser::{self, Serialize};
+use thiserror::Error;
+
+// -----------------------------------------------------------------------------
+// error types
+// -----------------------------------------------------------------------------
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum BracketError {
+    #[error("{0}")]
+    Custom(String),
+    #[error("{message} at {location}")]
+    Located { message: String, location: Location },
+    #[error("Unsupported value for the bracket format: {0}")]
+    Unsupported(&'static str),
+}
+
+impl BracketError {
+    fn at(self, location: &Location) -> Self {
+        match self {
+            BracketError::Custom(message) => BracketError::Located {
+                message,
+                location: location.clone(),
+            },
+            other => other,
+        }
+    }
+}
+
+impl de::Error for BracketError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        BracketError::Custom(msg.to_string())
+    }
+}
+
+impl ser::Error for BracketError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        BracketError::Custom(msg.to_string())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// deserialization
+// -----------------------------------------------------------------------------
+
+/// Читает произвольную структуру из текста в скобочном формате.
+///
+/// Секция соответствует структуре или словарю, пара `key = value` - полю.
+/// Нумерованные секции одного вида (`Button01`, `Button02`, ...) собираются в
+/// последовательность под именем без номера (`Button`).
+///
+/// Пустая последовательность в файле никак не представлена: секций нет, и
+/// поле для формата отсутствует. Поэтому поля-последовательности помечаются
+/// `#[serde(default)]`, иначе пустая панель, записанная [`to_string`], не
+/// прочитается обратно (`missing field`):
+///
+/// ```
+/// use megafon_cfg_viewer::bracket;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, PartialEq, Deserialize, Serialize)]
+/// struct Panel {
+///     #[serde(rename = "Button", default)]
+///     buttons: Vec<Button>,
+/// }
+///
+/// #[derive(Debug, PartialEq, Deserialize, Serialize)]
+/// struct Button {
+///     text: String,
+/// }
+///
+/// let panel: Panel = bracket::from_str("[Button01]\ntext = \"С-6\"\n[#Button01]\n").unwrap();
+/// assert_eq!(panel.buttons[0].text, "С-6");
+///
+/// let empty = Panel { buttons: Vec::new() };
+/// let text = bracket::to_string(&empty).unwrap();
+/// assert_eq!(bracket::from_str::<Panel>(&text).unwrap(), empty);
+/// ```
+pub fn from_str<T: DeserializeOwned>(input: &str) -> Result<T, BracketError> {
+    from_document(&Document::parse(input))
+}
+
+pub fn from_document<T: DeserializeOwned>(doc: &Document) -> Result<T, BracketError> {
+    T::deserialize(SectionDeserializer(&doc.root))
+}
+
+enum Item<'a> {
+    Value(&'a Entry),
+    Section(&'a Section),
+    Seq(Vec<&'a Section>),
+}
+
+/// Вид нумерованной секции без порядкового номера: `Button03` -> `Button`.
+fn split_numbered(name: &str) -> Option<&str> {
+    let kind = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    (kind.len() < name.len() && !kind.is_empty()).then_some(kind)
+}
+
+fn group_items(section: &Section) -> Vec<(&str, Item<'_>)> {
+    let mut items: Vec<(&str, Item)> = Vec::new();
+    for node in &section.children {
+        match node {
+            Node::Entry(entry) => items.push((entry.key.as_str(), Item::Value(entry))),
+            Node::Section(child) => match split_numbered(&child.name) {
+                Some(kind) => {
+                    let existing = items.iter_mut().find_map(|(key, item)| match item {
+                        Item::Seq(seq) if *key == kind => Some(seq),
+                        _ => None,
+                    });
+                    match existing {
+                        Some(seq) => seq.push(child),
+                        None => items.push((kind, Item::Seq(vec![child]))),
+                    }
+                }
+                None => items.push((child.name.as_str(), Item::Section(child))),
+            },
+            Node::Raw(_) => {}
+        }
+    }
+    items
+}
+
+struct SectionDeserializer<'a>(&'a Section);
+
+impl<'de> de::Deserializer<'de> for SectionDeserializer<'_> {
+    type Error = BracketError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BracketError> {
+        let access = SectionAccess {
+            items: group_items(self.0).into_iter(),
+            pending: None,
+        };
+        visitor
+            .visit_map(access)
+            .map_err(|err| err.at(&self.0.location))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BracketError> {
+        visitor.visit_some(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct SectionAccess<'a> {
+    items: std::vec::IntoIter<(&'a str, Item<'a>)>,
+    pending: Option<Item<'a>>,
+}
+
+impl<'de> MapAccess<'de> for SectionAccess<'_> {
+    type Error = BracketError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, BracketError> {
+        let Some((key, item)) = self.items.next() else {
+            return Ok(None);
+        };
+        self.pending = Some(item);
+        seed.deserialize(key.to_string().into_deserializer())
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, BracketError> {
+        match self.pending.take() {
+            Some(Item::Value(entry)) => seed.deserialize(ValueDeserializer(entry)),
+            Some(Item::Section(section)) => seed.deserialize(SectionDeserializer(section)),
+            Some(Item::Seq(sections)) => seed.deserialize(SeqDeserializer(sections)),
+            None => Err(BracketError::Custom(
+                "value requested before key".to_string(),
+            )),
+        }
+    }
+}
+
+struct SeqDeserializer<'a>(Vec<&'a Section>);
+
+impl<'de> de::Deserializer<'de> for SeqDeserializer<'_> {
+    type Error = BracketError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BracketError> {
+        let items = self.0.into_iter().map(SectionDeserializer);
+        visitor.visit_seq(de::value::SeqDeserializer::new(items))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BracketError> {
+        visitor.visit_some(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, 'a> IntoDeserializer<'de, BracketError> for SectionDeserializer<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct ValueDeserializer<'a>(&'a Entry);
+
+impl ValueDeserializer<'_> {
+    fn parse<T: std::str::FromStr>(&self, kind: &str) -> Result<T, BracketError>
+    where
+        T::Err: std::fmt::Display,
+    {
+        self.0.value().parse().map_err(|err| BracketError::Located {
+            message: format!("Invalid {}: {}", kind, err),
+            location: self.0.value_location(),
+        })
+    }
+
+    fn text(&self) -> String {
+        LineScanner::clean_string(self.0.value())
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident => $visit:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BracketError> {
+                visitor.$visit(self.parse::<$ty>(stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = BracketError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BracketError> {
+        let raw = self.0.value();
+        if raw.starts_with('"') {
+            visitor.visit_string(self.text())
+        } else if let Ok(v) = raw.parse::<i64>() {
+            visitor.visit_i64(v)
+        } else if let Ok(v) = raw.parse::<f64>() {
+            visitor.visit_f64(v)
+        } else {
+            visitor.visit_string(self.text())
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BracketError> {
+        match self.0.value() {
+            "1" | "true" => visitor.visit_bool(true),
+            "0" | "false" => visitor.visit_bool(false),
+            _ => Err(BracketError::Located {
+                message: "Invalid bool".to_string(),
+                location: self.0.value_location(),
+            }),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BracketError> {
+        visitor.visit_string(self.text())
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BracketError> {
+        visitor.visit_string(self.text())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BracketError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, BracketError> {
+        visitor.visit_enum(self.text().into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 char bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+// -----------------------------------------------------------------------------
+// serialization
+// -----------------------------------------------------------------------------
+
+/// Записывает структуру в скобочном формате. Поля-скаляры становятся парами
+/// `key = value`, вложенные структуры - секциями `[key]`, а последовательности
+/// структур - нумерованными секциями `[Key01]`, `[Key02]`, ...
+///
+/// Пустая последовательность не даёт ни одной секции; см. [`from_str`] о том,
+/// как читать такие поля обратно.
+pub fn to_string<T: Serialize>(value: &T) -> Result<String, BracketError> {
+    match value.serialize(ValueSerializer)? {
+        Value::Section(fields) => {
+            let mut writer = LineWriter::new();
+            write_fields(&mut writer, &fields)?;
+            Ok(writer.finish())
+        }
+        _ => Err(BracketError::Unsupported(
+            "top-level value must be a struct or map",
+        )),
+    }
+}
+
+enum Value {
+    Scalar(String),
+    Section(Vec<(String, Value)>),
+    Seq(Vec<Value>),
+    Skip,
+}
+
+fn write_fields(writer: &mut LineWriter, fields: &[(String, Value)]) -> Result<(), BracketError> {
+    for (key, value) in fields {
+        match value {
+            Value::Scalar(text) => writer.write_kv(key, text),
+            Value::Section(inner) => {
+                writer.open_section(key);
+                write_fields(writer, inner)?;
+                writer.close_section(key);
+            }
+            Value::Seq(items) => {
+                for (idx, item) in items.iter().enumerate() {
+                    let Value::Section(inner) = item else {
+                        return Err(BracketError::Unsupported(
+                            "sequences may contain only structs or maps",
+                        ));
+                    };
+                    let name = format!("{}{:02}", key, idx + 1);
+                    writer.open_section(&name);
+                    write_fields(writer, inner)?;
+                    writer.close_section(&name);
+                }
+            }
+            Value::Skip => {}
+        }
+    }
+    Ok(())
+}
+
+struct ValueSerializer;
+
+struct SeqSerializer(Vec<Value>);
+
+struct MapSerializer {
+    fields: Vec<(String, Value)>,
+    key: Option<String>,
+}
+
+fn scalar(value: impl std::fmt::Display) -> Result<Value, BracketError> {
+    Ok(Value::Scalar(value.to_string()))
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = BracketError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = ser::Impossible<Value, BracketError>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = ser::Impossible<Value, BracketError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, BracketError> {
+        scalar(u8::from(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Value, BracketError> {
+        scalar(v)
+    }
+    fn serialize_i16(self, v: i16) -> Result<Value, BracketError> {
+        scalar(v)
+    }
+    fn serialize_i32(self, v: i32) -> Result<Value, BracketError> {
+        scalar(v)
+    }
+    fn serialize_i64(self, v: i64) -> Result<Value, BracketError> {
+        scalar(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<Value, BracketError> {
+        scalar(v)
+    }
+    fn serialize_u16(self, v: u16) -> Result<Value, BracketError> {
+        scalar(v)
+    }
+    fn serialize_u32(self, v: u32) -> Result<Value, BracketError> {
+        scalar(v)
+    }
+    fn serialize_u64(self, v: u64) -> Result<Value, BracketError> {
+        scalar(v)
+    }
+    fn serialize_f32(self, v: f32) -> Result<Value, BracketError> {
+        scalar(v)
+    }
+    fn serialize_f64(self, v: f64) -> Result<Value, BracketError> {
+        scalar(v)
+    }
+    fn serialize_char(self, v: char) -> Result<Value, BracketError> {
+        self.serialize_str(&v.to_string())
+    }
+    fn serialize_str(self, v: &str) -> Result<Value, BracketError> {
+        scalar(LineWriter::quote_string(v))
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Value, BracketError> {
+        Err(BracketError::Unsupported("bytes"))
+    }
+    fn serialize_none(self) -> Result<Value, BracketError> {
+        Ok(Value::Skip)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, BracketError> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Value, BracketError> {
+        Ok(Value::Skip)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, BracketError> {
+        Ok(Value::Skip)
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, BracketError> {
+        self.serialize_str(variant)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, BracketError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Value, BracketError> {
+        Err(BracketError::Unsupported("enum variant with data"))
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, BracketError> {
+        Ok(SeqSerializer(Vec::with_capacity(len.unwrap_or(0))))
+    }
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, BracketError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, BracketError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, BracketError> {
+        Err(BracketError::Unsupported("enum variant with data"))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, BracketError> {
+        Ok(MapSerializer {
+            fields: Vec::new(),
+            key: None,
+        })
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer, BracketError> {
+        self.serialize_map(Some(len))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, BracketError> {
+        Err(BracketError::Unsupported("enum variant with data"))
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = BracketError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), BracketError> {
+        self.0.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, BracketError> {
+        Ok(Value::Seq(self.0))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = BracketError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), BracketError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, BracketError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = BracketError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), BracketError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, BracketError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = BracketError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), BracketError> {
+        match key.serialize(ValueSerializer)? {
+            Value::Scalar(text) => {
+                self.key = Some(LineScanner::clean_string(&text));
+                Ok(())
+            }
+            _ => Err(BracketError::Unsupported("map keys must be scalars")),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), BracketError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| BracketError::Custom("value serialized before key".to_string()))?;
+        self.fields.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, BracketError> {
+        Ok(Value::Section(self.fields))
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Value;
+    type Error = BracketError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), BracketError> {
+        self.fields
+            .push((key.to_string(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, BracketError> {
+        Ok(Value::Section(self.fields))
+    }
+}
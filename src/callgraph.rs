@@ -0,0 +1,192 @@
+use crate::config::ScreenConfig;
+use crate::fleet::Fleet;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+// -----------------------------------------------------------------------------
+// call graph
+// -----------------------------------------------------------------------------
+
+/// Абонент в графе вызовов. `workstation` - есть ли для этого адреса своя
+/// конфигурация; остальные адреса известны только по кнопкам.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Subscriber {
+    pub name: Option<String>,
+    pub workstation: bool,
+}
+
+/// Ориентированный граф "кто кому может позвонить" по всей площадке.
+///
+/// Вершины - внутренние номера: глобальные `internal_address` конфигураций и
+/// адреса кнопок телефонных панелей. Ребро `a -> b` означает, что на рабочем
+/// месте `a` есть кнопка вызова `b`; подписи этих кнопок хранятся на ребре.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallGraph {
+    nodes: BTreeMap<u32, Subscriber>,
+    edges: BTreeMap<u32, BTreeMap<u32, Vec<String>>>,
+}
+
+impl CallGraph {
+    /// Строит граф по набору конфигураций. Конфигурации без глобального
+    /// `internal_address` не могут быть источником вызова и пропускаются.
+    pub fn from_configs<'a>(configs: impl IntoIterator<Item = &'a ScreenConfig>) -> Self {
+        let mut graph = CallGraph::default();
+        for config in configs {
+            let Some(from) = config.internal_address else {
+                continue;
+            };
+            let node = graph.nodes.entry(from).or_default();
+            node.workstation = true;
+            if config.name.is_some() {
+                node.name = config.name.clone();
+            }
+            for button in config.phone_panels.iter().flat_map(|p| &p.buttons) {
+                let to = button.internal_address;
+                if to == 0 {
+                    continue;
+                }
+                let callee = graph.nodes.entry(to).or_default();
+                if callee.name.is_none() && !button.text.is_empty() {
+                    callee.name = Some(button.text.clone());
+                }
+                let labels = graph.edges.entry(from).or_default().entry(to).or_default();
+                if !labels.contains(&button.text) {
+                    labels.push(button.text.clone());
+                }
+            }
+        }
+        graph
+    }
+
+    pub fn from_fleet(fleet: &Fleet) -> Self {
+        Self::from_configs(fleet.workstations.iter().map(|ws| ws.config()))
+    }
+
+    pub fn subscriber(&self, address: u32) -> Option<&Subscriber> {
+        self.nodes.get(&address)
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = u32> + '_ {
+        self.nodes.keys().copied()
+    }
+
+    /// Адреса, для которых загружена конфигурация рабочего места.
+    pub fn workstations(&self) -> impl Iterator<Item = u32> + '_ {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.workstation)
+            .map(|(&address, _)| address)
+    }
+
+    /// Кого можно вызвать с рабочего места `from` напрямую.
+    pub fn callees(&self, from: u32) -> impl Iterator<Item = u32> + '_ {
+        self.edges
+            .get(&from)
+            .into_iter()
+            .flat_map(|targets| targets.keys().copied())
+    }
+
+    /// Рабочие места, с которых есть кнопка вызова `to`.
+    pub fn callers(&self, to: u32) -> Vec<u32> {
+        self.edges
+            .iter()
+            .filter(|(_, targets)| targets.contains_key(&to))
+            .map(|(&from, _)| from)
+            .collect()
+    }
+
+    /// Подписи кнопок, которыми `from` вызывает `to`.
+    pub fn labels(&self, from: u32, to: u32) -> &[String] {
+        self.edges
+            .get(&from)
+            .and_then(|targets| targets.get(&to))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Кратчайшая цепочка вызовов от `from` до `to` (включая оба конца).
+    /// Дозвон идёт через промежуточных абонентов, у которых есть своя
+    /// конфигурация.
+    pub fn path(&self, from: u32, to: u32) -> Option<Vec<u32>> {
+        let mut previous: BTreeMap<u32, u32> = BTreeMap::new();
+        let mut queue = VecDeque::from([from]);
+        let mut seen = BTreeSet::from([from]);
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = vec![to];
+                let mut current = to;
+                while let Some(&prev) = previous.get(&current) {
+                    path.push(prev);
+                    current = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.callees(node) {
+                if seen.insert(next) {
+                    previous.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Все адреса, до которых можно дозвониться с `from` напрямую или через
+    /// других абонентов.
+    pub fn reachable_from(&self, from: u32) -> BTreeSet<u32> {
+        let mut seen = BTreeSet::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(node) = queue.pop_front() {
+            for next in self.callees(node) {
+                if seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        seen
+    }
+
+    pub fn can_reach(&self, from: u32, to: u32) -> bool {
+        from == to || self.reachable_from(from).contains(&to)
+    }
+
+    /// Рабочие места, которые не могут дозвониться до `to` ни напрямую, ни
+    /// через других абонентов.
+    pub fn cannot_reach(&self, to: u32) -> Vec<u32> {
+        self.workstations()
+            .filter(|&from| !self.can_reach(from, to))
+            .collect()
+    }
+
+    /// Граф в формате Graphviz DOT. Рабочие места изображаются
+    /// прямоугольниками, прочие абоненты - овалами.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph calls {\n    rankdir=LR;\n");
+        for (address, node) in &self.nodes {
+            let label = match &node.name {
+                Some(name) => format!("{}\\n{}", address, dot_escape(name)),
+                None => address.to_string(),
+            };
+            let shape = if node.workstation { "box" } else { "ellipse" };
+            out += &format!(
+                "    \"{}\" [label=\"{}\", shape={}];\n",
+                address, label, shape
+            );
+        }
+        for (from, targets) in &self.edges {
+            for (to, labels) in targets {
+                let label = labels
+                    .iter()
+                    .map(|l| dot_escape(l))
+                    .collect::<Vec<_>>()
+                    .join("\\n");
+                out += &format!("    \"{}\" -> \"{}\" [label=\"{}\"];\n", from, to, label);
+            }
+        }
+        out += "}\n";
+        out
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
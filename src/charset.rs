@@ -0,0 +1,366 @@
+use encoding::all::{KOI8_R, WINDOWS_1251};
+use encoding::{DecoderTrap, EncodingRef};
+use thiserror::Error;
+
+/// Кодировка файла конфигурации.
+///
+/// Пульт пишет файлы в KOI8-R, но конфигурации со старых рабочих мест бывают
+/// сохранены в CP1251, а отредактированные вручную - в UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Koi8R,
+    Cp1251,
+    Utf8 { bom: bool },
+}
+
+impl std::fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextEncoding::Koi8R => f.write_str("KOI8-R"),
+            TextEncoding::Cp1251 => f.write_str("CP1251"),
+            TextEncoding::Utf8 { bom: false } => f.write_str("UTF-8"),
+            TextEncoding::Utf8 { bom: true } => f.write_str("UTF-8 with BOM"),
+        }
+    }
+}
+
+impl std::str::FromStr for TextEncoding {
+    type Err = CharsetError;
+
+    /// Разбирает имя кодировки: `koi8-r`, `cp1251` (`windows-1251`), `utf-8`
+    /// или `utf-8-bom`. Регистр и дефисы не учитываются.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_'))
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "koi8r" => Ok(TextEncoding::Koi8R),
+            "cp1251" | "windows1251" => Ok(TextEncoding::Cp1251),
+            "utf8" => Ok(TextEncoding::Utf8 { bom: false }),
+            "utf8bom" => Ok(TextEncoding::Utf8 { bom: true }),
+            _ => Err(CharsetError::UnknownEncoding(s.to_string())),
+        }
+    }
+}
+
+/// Декодированный текст вместе с выбранной кодировкой и смещениями байтов,
+/// которые пришлось заменить на `U+FFFD`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedText {
+    pub text: String,
+    pub encoding: TextEncoding,
+    pub invalid_offsets: Vec<usize>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum CharsetError {
+    #[error("Byte {byte:#04x} at offset {offset} is not valid {encoding}")]
+    InvalidByte {
+        offset: usize,
+        byte: u8,
+        encoding: TextEncoding,
+    },
+    #[error("Unknown encoding: {0}")]
+    UnknownEncoding(String),
+}
+
+/// Что делать с символами, которых нет в целевой кодировке.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SubstitutionPolicy {
+    /// Ничего не заменять: каждый такой символ - ошибка.
+    #[default]
+    Strict,
+    /// Заменять типографские знаки и буквы других кириллических алфавитов по
+    /// таблице, а всё остальное - на `?`.
+    Substitute,
+}
+
+/// Символ, который не удалось записать в целевой кодировке.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unrepresentable {
+    pub offset: usize,
+    pub ch: char,
+    pub replacement: Option<&'static str>,
+}
+
+/// Закодированный текст и символы, которых нет в целевой кодировке.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedText {
+    pub bytes: Vec<u8>,
+    pub unrepresentable: Vec<Unrepresentable>,
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+const FALLBACK_REPLACEMENT: &str = "?";
+
+// Замены для символов, которые чаще всего попадают в подписи кнопок при
+// копировании из Word, и для букв украинского и белорусского алфавитов.
+const SUBSTITUTIONS: [(char, &str); 25] = [
+    ('«', "\""),
+    ('»', "\""),
+    ('“', "\""),
+    ('”', "\""),
+    ('„', "\""),
+    ('‘', "'"),
+    ('’', "'"),
+    ('‚', "'"),
+    ('—', "-"),
+    ('–', "-"),
+    ('‑', "-"),
+    ('−', "-"),
+    ('…', "..."),
+    ('\u{a0}', " "),
+    ('№', "N"),
+    ('і', "i"),
+    ('І', "I"),
+    ('ї', "i"),
+    ('Ї', "I"),
+    ('є', "е"),
+    ('Є', "Е"),
+    ('ґ', "г"),
+    ('Ґ', "Г"),
+    ('ў', "у"),
+    ('Ў', "У"),
+];
+
+// Частоты букв русского языка (на тысячу букв), по ним выбирается
+// однобайтовая кодировка, в которой текст выглядит осмысленно.
+const LETTER_WEIGHTS: [(char, u32); 33] = [
+    ('о', 110),
+    ('е', 85),
+    ('а', 80),
+    ('и', 74),
+    ('н', 67),
+    ('т', 63),
+    ('с', 55),
+    ('р', 47),
+    ('в', 45),
+    ('л', 44),
+    ('к', 35),
+    ('м', 32),
+    ('д', 30),
+    ('п', 28),
+    ('у', 26),
+    ('я', 20),
+    ('ы', 19),
+    ('ь', 17),
+    ('г', 17),
+    ('з', 16),
+    ('б', 16),
+    ('ч', 14),
+    ('й', 12),
+    ('х', 10),
+    ('ж', 9),
+    ('ш', 7),
+    ('ю', 6),
+    ('ц', 5),
+    ('щ', 4),
+    ('э', 3),
+    ('ф', 3),
+    ('ъ', 1),
+    ('ё', 1),
+];
+
+// Штраф за строчную букву, за которой внутри слова идёт заглавная. При чтении
+// KOI8-R как CP1251 (и наоборот) регистр букв инвертируется, поэтому такие
+// пары - надёжный признак неверной кодировки.
+const CASE_FLIP_PENALTY: i64 = 50;
+
+/// Определяет кодировку по содержимому файла.
+///
+/// BOM и корректный UTF-8 с не-ASCII символами дают UTF-8. Между KOI8-R и
+/// CP1251 выбирается та, в которой текст больше похож на русский. Чистый
+/// ASCII считается KOI8-R - родной кодировкой пульта.
+pub fn detect_encoding(bytes: &[u8]) -> TextEncoding {
+    if bytes.starts_with(UTF8_BOM) {
+        return TextEncoding::Utf8 { bom: true };
+    }
+    if bytes.is_ascii() {
+        return TextEncoding::Koi8R;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return TextEncoding::Utf8 { bom: false };
+    }
+
+    let koi8 = decode_lossy(bytes, TextEncoding::Koi8R);
+    let cp1251 = decode_lossy(bytes, TextEncoding::Cp1251);
+    if russian_score(&cp1251.text) > russian_score(&koi8.text) {
+        TextEncoding::Cp1251
+    } else {
+        TextEncoding::Koi8R
+    }
+}
+
+/// Декодирует текст в заданной кодировке, останавливаясь на первом
+/// недопустимом байте.
+pub fn decode(bytes: &[u8], encoding: TextEncoding) -> Result<String, CharsetError> {
+    let decoded = decode_lossy(bytes, encoding);
+    match decoded.invalid_offsets.first() {
+        Some(&offset) => Err(CharsetError::InvalidByte {
+            offset,
+            byte: bytes[offset],
+            encoding,
+        }),
+        None => Ok(decoded.text),
+    }
+}
+
+/// Декодирует текст, заменяя недопустимые байты на `U+FFFD` и запоминая
+/// их смещения.
+pub fn decode_lossy(bytes: &[u8], encoding: TextEncoding) -> DecodedText {
+    let (text, invalid_offsets) = match encoding {
+        TextEncoding::Koi8R => decode_single_byte(bytes, KOI8_R),
+        TextEncoding::Cp1251 => decode_single_byte(bytes, WINDOWS_1251),
+        TextEncoding::Utf8 { bom } => {
+            let skip = if bom && bytes.starts_with(UTF8_BOM) {
+                UTF8_BOM.len()
+            } else {
+                0
+            };
+            decode_utf8(&bytes[skip..], skip)
+        }
+    };
+    DecodedText {
+        text,
+        encoding,
+        invalid_offsets,
+    }
+}
+
+/// Определяет кодировку и декодирует текст без потерь.
+pub fn decode_auto(bytes: &[u8]) -> Result<DecodedText, CharsetError> {
+    let encoding = detect_encoding(bytes);
+    Ok(DecodedText {
+        text: decode(bytes, encoding)?,
+        encoding,
+        invalid_offsets: Vec::new(),
+    })
+}
+
+/// Определяет кодировку и декодирует текст, заменяя недопустимые байты.
+pub fn decode_auto_lossy(bytes: &[u8]) -> DecodedText {
+    decode_lossy(bytes, detect_encoding(bytes))
+}
+
+/// Кодирует текст в заданной кодировке. Символы, которых в ней нет, заменяются
+/// согласно `policy` (при [`SubstitutionPolicy::Strict`] - на `?`) и
+/// перечисляются в [`EncodedText::unrepresentable`].
+pub fn encode(text: &str, encoding: TextEncoding, policy: SubstitutionPolicy) -> EncodedText {
+    let table = match encoding {
+        TextEncoding::Koi8R => single_byte_table(KOI8_R),
+        TextEncoding::Cp1251 => single_byte_table(WINDOWS_1251),
+        TextEncoding::Utf8 { bom } => {
+            let mut bytes = if bom { UTF8_BOM.to_vec() } else { Vec::new() };
+            bytes.extend_from_slice(text.as_bytes());
+            return EncodedText {
+                bytes,
+                unrepresentable: Vec::new(),
+            };
+        }
+    };
+    let encode_char = |c: char| {
+        table
+            .iter()
+            .position(|entry| *entry == Some(c))
+            .and_then(|b| u8::try_from(b).ok())
+    };
+
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut unrepresentable = Vec::new();
+    for (offset, ch) in text.char_indices() {
+        if let Some(b) = encode_char(ch) {
+            bytes.push(b);
+            continue;
+        }
+        let replacement = match policy {
+            SubstitutionPolicy::Strict => None,
+            SubstitutionPolicy::Substitute => Some(
+                SUBSTITUTIONS
+                    .iter()
+                    .find(|(from, _)| *from == ch)
+                    .map_or(FALLBACK_REPLACEMENT, |(_, to)| *to),
+            ),
+        };
+        let fallback = replacement.unwrap_or(FALLBACK_REPLACEMENT);
+        bytes.extend(fallback.chars().filter_map(encode_char));
+        unrepresentable.push(Unrepresentable {
+            offset,
+            ch,
+            replacement,
+        });
+    }
+    EncodedText {
+        bytes,
+        unrepresentable,
+    }
+}
+
+fn single_byte_table(encoding: EncodingRef) -> Vec<Option<char>> {
+    (0..=u8::MAX)
+        .map(|b| {
+            encoding
+                .decode(&[b], DecoderTrap::Strict)
+                .ok()
+                .and_then(|s| s.chars().next())
+        })
+        .collect()
+}
+
+fn decode_single_byte(bytes: &[u8], encoding: EncodingRef) -> (String, Vec<usize>) {
+    let table = single_byte_table(encoding);
+    let mut text = String::with_capacity(bytes.len());
+    let mut invalid = Vec::new();
+    for (offset, &b) in bytes.iter().enumerate() {
+        match table[b as usize] {
+            Some(c) => text.push(c),
+            None => {
+                text.push(char::REPLACEMENT_CHARACTER);
+                invalid.push(offset);
+            }
+        }
+    }
+    (text, invalid)
+}
+
+fn decode_utf8(mut bytes: &[u8], mut base: usize) -> (String, Vec<usize>) {
+    let mut text = String::with_capacity(bytes.len());
+    let mut invalid = Vec::new();
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(valid) => {
+                text.push_str(valid);
+                return (text, invalid);
+            }
+            Err(err) => {
+                let valid_up_to = err.valid_up_to();
+                let bad_len = err.error_len().unwrap_or(bytes.len() - valid_up_to);
+                text.push_str(&String::from_utf8_lossy(&bytes[..valid_up_to]));
+                text.push(char::REPLACEMENT_CHARACTER);
+                invalid.extend((0..bad_len).map(|i| base + valid_up_to + i));
+                bytes = &bytes[valid_up_to + bad_len..];
+                base += valid_up_to + bad_len;
+            }
+        }
+    }
+}
+
+fn russian_score(text: &str) -> i64 {
+    let mut score = 0;
+    let mut prev: Option<char> = None;
+    for c in text.chars() {
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        if let Some((_, weight)) = LETTER_WEIGHTS.iter().find(|(l, _)| *l == lower) {
+            score += i64::from(*weight);
+            if prev.is_some_and(|p| p.is_lowercase()) && c.is_uppercase() {
+                score -= CASE_FLIP_PENALTY;
+            }
+            prev = Some(c);
+        } else {
+            prev = None;
+        }
+    }
+    score
+}
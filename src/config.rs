@@ -1,4 +1,11 @@
-use crate::generic::LineScanner;
+use crate::charset::{self, CharsetError, SubstitutionPolicy, TextEncoding};
+use crate::generic::{
+    Document, Entry, LineScanner, LineWriter, Location, Node, RawLine, Section, StructureIssue,
+    TreeOptions,
+};
+use serde::{Deserialize, Serialize};
+use std::num::{ParseFloatError, ParseIntError};
+use std::str::FromStr;
 use thiserror::Error;
 
 // -----------------------------------------------------------------------------
@@ -14,7 +21,8 @@ use thiserror::Error;
 /// * [`available_radiostations`] - доступные радиостанции на рабочем месте.
 /// * [`phone_panels`] - список панелей с кнопками оперативного вызова.
 /// * [`radio_panels`] - список панелей с кнопками радиостанций.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ScreenConfig {
     pub internal_address: Option<u32>,
     pub name: Option<String>,
@@ -23,20 +31,20 @@ pub struct ScreenConfig {
     pub radio_panels: Vec<RadioPanel>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct AvailableRadiostation {
     pub id: String,
     pub radio_name: String,
     pub slot: i32,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct PhonePanel {
     pub id: String,
     pub buttons: Vec<PhoneButton>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct PhoneButton {
     pub id: String,
     pub internal_address: u32,
@@ -47,13 +55,13 @@ pub struct PhoneButton {
     pub text: String,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct RadioPanel {
     pub id: String,
     pub buttons: Vec<RadioButton>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct RadioButton {
     pub id: String,
     pub position_x: f32,
@@ -68,14 +76,56 @@ pub struct RadioButton {
 // error types
 // -----------------------------------------------------------------------------
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum ConfigError {
-    #[error("Invalid integer: {0}")]
-    InvalidInt(#[from] std::num::ParseIntError),
-    #[error("Invalid float: {0}")]
-    InvalidFloat(#[from] std::num::ParseFloatError),
-    #[error("Unknown global key: {0}")]
-    UnknownGlobalKey(String),
+    #[error("Invalid integer at {location}: {source}")]
+    InvalidInt {
+        source: ParseIntError,
+        location: Location,
+    },
+    #[error("Invalid float at {location}: {source}")]
+    InvalidFloat {
+        source: ParseFloatError,
+        location: Location,
+    },
+    #[error("Unknown global key at {location}: {key}")]
+    UnknownGlobalKey { key: String, location: Location },
+    #[error("Unknown key at {location}: {key}")]
+    UnknownKey { key: String, location: Location },
+    #[error("Unexpected section at {location}: {name}")]
+    UnexpectedSection { name: String, location: Location },
+    #[error("Missing required field at {location}: {field}")]
+    MissingField { field: String, location: Location },
+    #[error("Section [{name}] opened at {location} is never closed")]
+    UnclosedSection { name: String, location: Location },
+    #[error("Section [{expected}] opened at {location} is closed by [#{found}] on line {line}")]
+    MismatchedClosingTag {
+        expected: String,
+        found: String,
+        line: usize,
+        location: Location,
+    },
+    #[error("Closing tag without an open section at {location}")]
+    StrayClosingTag { name: String, location: Location },
+    #[error("Unrecognized line at {location}")]
+    UnrecognizedLine { location: Location },
+}
+
+impl ConfigError {
+    pub fn location(&self) -> &Location {
+        match self {
+            ConfigError::InvalidInt { location, .. }
+            | ConfigError::InvalidFloat { location, .. }
+            | ConfigError::UnknownGlobalKey { location, .. }
+            | ConfigError::UnknownKey { location, .. }
+            | ConfigError::UnexpectedSection { location, .. }
+            | ConfigError::MissingField { location, .. }
+            | ConfigError::UnclosedSection { location, .. }
+            | ConfigError::MismatchedClosingTag { location, .. }
+            | ConfigError::StrayClosingTag { location, .. }
+            | ConfigError::UnrecognizedLine { location } => location,
+        }
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -97,234 +147,985 @@ const SIZE_WIDTH: &str = "size_width";
 const TEXT: &str = "text";
 const RADIO_PANELS: &str = "RadioPanels";
 const NAME: &str = "name";
+const MASTER_VOLUME_SHOW: &str = "master_volume_show";
+
+const TOP_LEVEL_SECTIONS: [&str; 3] = [AVAILABLE_RADIOSTATIONS, PHONE_PANELS, RADIO_PANELS];
+
+/// Глобальные ключи, которые пульт понимает, а модель не хранит. Строгий
+/// разбор не считает их неизвестными; в файле они остаются как есть.
+const IGNORED_GLOBAL_KEYS: [&str; 1] = [MASTER_VOLUME_SHOW];
+
+const REQUIRED_RADIOSTATION_KEYS: [&str; 2] = [RADIO_NAME, SLOT];
+const REQUIRED_PHONE_BUTTON_KEYS: [&str; 6] = [
+    INTERNAL_ADDRESS,
+    POSITION_X,
+    POSITION_Y,
+    SIZE_HEIGHT,
+    SIZE_WIDTH,
+    TEXT,
+];
+const REQUIRED_RADIO_BUTTON_KEYS: [&str; 6] =
+    [POSITION_X, POSITION_Y, SIZE_HEIGHT, SIZE_WIDTH, SLOT, TEXT];
 
 // -----------------------------------------------------------------------------
 // parsing logic
 // -----------------------------------------------------------------------------
 
+/// Настройки разбора конфигурации.
+///
+/// * [`recover`] - не прерывать разбор на первой ошибке: некорректное значение
+///   пропускается, а ошибка попадает в список диагностик.
+/// * [`strict`] - считать ошибками неизвестные ключи, неожиданные секции и
+///   отсутствие обязательных полей. Используется для проверки перед выкладкой.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    pub recover: bool,
+    pub strict: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub error: ConfigError,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning: {}", self.error),
+            Severity::Error => write!(f, "error: {}", self.error),
+        }
+    }
+}
+
+/// Результат разбора: конфигурация (возможно, неполная) и найденные проблемы.
+#[derive(Debug, Clone, Default)]
+pub struct ParseReport {
+    pub config: ScreenConfig,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseReport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+}
+
 pub fn parse(input: &str) -> Result<ScreenConfig, ConfigError> {
-    let mut scanner = LineScanner::new(input);
-    let mut config = ScreenConfig::default();
-
-    while let Some(line) = scanner.peek_line() {
-        if let Some(section) = LineScanner::get_section_name(line) {
-            scanner.next_line();
-            match section {
-                AVAILABLE_RADIOSTATIONS => {
-                    config.available_radiostations = parse_radiostations(&mut scanner)?
+    parse_with_options(input, ParseOptions::default()).map(|report| report.config)
+}
+
+/// Разбирает конфигурацию целиком, собирая все ошибки и предупреждения
+/// вместо остановки на первой из них.
+pub fn parse_lenient(input: &str) -> ParseReport {
+    let options = ParseOptions {
+        recover: true,
+        ..Default::default()
+    };
+    parse_with_options(input, options).unwrap_or_default()
+}
+
+pub fn parse_with_options(input: &str, options: ParseOptions) -> Result<ParseReport, ConfigError> {
+    parse_document_with_options(&parse_document(input), options)
+}
+
+/// Строит дерево документа с учётом того, что однородные секции
+/// конфигурации (`[Button03]`, `[Button04]`, секции верхнего уровня) не
+/// вкладываются друг в друга: пропущенный закрывающий тег восстанавливается
+/// по следующему открывающему.
+pub fn parse_document(input: &str) -> Document {
+    Document::parse_with_options(
+        input,
+        TreeOptions {
+            close_siblings: true,
+            sibling_groups: &[&TOP_LEVEL_SECTIONS],
+        },
+    )
+}
+
+/// Собирает [`ScreenConfig`] из уже построенного дерева документа.
+pub fn parse_document_with_options(
+    doc: &Document,
+    options: ParseOptions,
+) -> Result<ParseReport, ConfigError> {
+    let mut parser = Parser {
+        options,
+        diagnostics: Vec::new(),
+    };
+    for issue in &doc.issues {
+        parser.report(Severity::Error, structure_error(issue))?;
+    }
+    let config = parser.parse_screen(&doc.root)?;
+    Ok(ParseReport {
+        config,
+        diagnostics: parser.diagnostics,
+    })
+}
+
+fn structure_error(issue: &StructureIssue) -> ConfigError {
+    match issue.clone() {
+        StructureIssue::Unclosed { name, location } => {
+            ConfigError::UnclosedSection { name, location }
+        }
+        StructureIssue::Mismatched {
+            expected,
+            found,
+            line,
+            location,
+        } => ConfigError::MismatchedClosingTag {
+            expected,
+            found,
+            line,
+            location,
+        },
+        StructureIssue::StrayClosing { name, location } => {
+            ConfigError::StrayClosingTag { name, location }
+        }
+    }
+}
+
+struct Parser {
+    options: ParseOptions,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Parser {
+    fn report(&mut self, severity: Severity, error: ConfigError) -> Result<(), ConfigError> {
+        if severity == Severity::Error && !self.options.recover {
+            return Err(error);
+        }
+        self.diagnostics.push(Diagnostic { severity, error });
+        Ok(())
+    }
+
+    /// Разбирает целое значение. `None` - значение некорректно, ошибка уже
+    /// записана в диагностику.
+    fn parse_int<T>(&mut self, entry: &Entry) -> Result<Option<T>, ConfigError>
+    where
+        T: FromStr<Err = ParseIntError>,
+    {
+        match entry.value().parse() {
+            Ok(v) => Ok(Some(v)),
+            Err(source) => {
+                let location = entry.value_location();
+                self.report(
+                    Severity::Error,
+                    ConfigError::InvalidInt { source, location },
+                )?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn read_int<T>(&mut self, entry: &Entry, target: &mut T) -> Result<(), ConfigError>
+    where
+        T: FromStr<Err = ParseIntError>,
+    {
+        if let Some(v) = self.parse_int(entry)? {
+            *target = v;
+        }
+        Ok(())
+    }
+
+    fn read_float(&mut self, entry: &Entry, target: &mut f32) -> Result<(), ConfigError> {
+        match entry.value().parse() {
+            Ok(v) => *target = v,
+            Err(source) => {
+                let location = entry.value_location();
+                self.report(
+                    Severity::Error,
+                    ConfigError::InvalidFloat { source, location },
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unknown_key(&mut self, entry: &Entry) -> Result<(), ConfigError> {
+        if self.options.strict {
+            let key = entry.key.clone();
+            let location = entry.location.clone();
+            self.report(Severity::Error, ConfigError::UnknownKey { key, location })?;
+        }
+        Ok(())
+    }
+
+    fn unexpected_section(&mut self, section: &Section) -> Result<(), ConfigError> {
+        if self.options.strict {
+            let name = section.name.clone();
+            let location = section.location.clone();
+            self.report(
+                Severity::Error,
+                ConfigError::UnexpectedSection { name, location },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Предупреждает о строке, которая не является ни тегом секции, ни парой
+    /// `key = value`. Закрывающие теги без пары уже учтены при построении дерева.
+    fn unrecognized(&mut self, raw: &RawLine) -> Result<(), ConfigError> {
+        let line = raw.text.trim();
+        if line.is_empty() || LineScanner::get_closing_name(line).is_some() {
+            return Ok(());
+        }
+        let location = raw.location.clone();
+        self.report(
+            Severity::Warning,
+            ConfigError::UnrecognizedLine { location },
+        )
+    }
+
+    /// Разбирает содержимое секции, в которой ожидаются только вложенные
+    /// секции вида `prefix`.
+    fn parse_children<T>(
+        &mut self,
+        section: &Section,
+        prefix: &str,
+        mut parse_child: impl FnMut(&mut Self, &Section) -> Result<T, ConfigError>,
+    ) -> Result<Vec<T>, ConfigError> {
+        let mut items = Vec::new();
+        for node in &section.children {
+            match node {
+                Node::Section(child) if child.name.starts_with(prefix) => {
+                    items.push(parse_child(self, child)?)
                 }
-                PHONE_PANELS => config.phone_panels = parse_phone_panels(&mut scanner)?,
-                RADIO_PANELS => config.radio_panels = parse_radio_panels(&mut scanner)?,
-                _ => consume_block(&mut scanner, section),
+                Node::Section(child) => self.unexpected_section(child)?,
+                Node::Entry(entry) => self.unknown_key(entry)?,
+                Node::Raw(raw) => self.unrecognized(raw)?,
             }
-        } else if let Some((key, value)) = LineScanner::parse_kv(line) {
-            scanner.next_line();
-            match key {
-                INTERNAL_ADDRESS => config.internal_address = Some(value.parse()?),
-                NAME => config.name = Some(LineScanner::clean_string(value)),
-                _ => {} // return Err(ConfigError::UnknownGlobalKey(key.to_string())),
+        }
+        Ok(items)
+    }
+
+    /// Разбирает ключи секции-листа, передавая каждый известный ключ в `read`.
+    fn parse_keys(
+        &mut self,
+        section: &Section,
+        required: &[&str],
+        mut read: impl FnMut(&mut Self, &Entry) -> Result<bool, ConfigError>,
+    ) -> Result<(), ConfigError> {
+        let mut seen = Vec::new();
+        for node in &section.children {
+            match node {
+                Node::Entry(entry) => {
+                    seen.push(entry.key.as_str());
+                    if !read(self, entry)? {
+                        self.unknown_key(entry)?;
+                    }
+                }
+                Node::Section(child) => self.unexpected_section(child)?,
+                Node::Raw(raw) => self.unrecognized(raw)?,
             }
-        } else {
-            scanner.next_line();
         }
+        self.require_keys(&seen, required, &section.location)
     }
-    Ok(config)
-}
 
-fn parse_radiostations(
-    scanner: &mut LineScanner,
-) -> Result<Vec<AvailableRadiostation>, ConfigError> {
-    let mut stations = Vec::new();
-    while let Some(line) = scanner.peek_line() {
-        if LineScanner::is_closing_tag(line, AVAILABLE_RADIOSTATIONS) {
-            scanner.next_line();
-            break;
+    fn require_keys(
+        &mut self,
+        seen: &[&str],
+        required: &[&str],
+        section: &Location,
+    ) -> Result<(), ConfigError> {
+        if !self.options.strict {
+            return Ok(());
         }
+        for field in required.iter().filter(|key| !seen.contains(key)) {
+            let location = Location {
+                path: format!("{}/{}", section.path, field),
+                ..section.clone()
+            };
+            let field = field.to_string();
+            self.report(
+                Severity::Error,
+                ConfigError::MissingField { field, location },
+            )?;
+        }
+        Ok(())
+    }
 
-        if let Some(id) = LineScanner::get_section_name(line) {
-            if id.starts_with(AVAILABLE_RADIOSTATION) {
-                scanner.next_line();
-                let mut station = AvailableRadiostation {
-                    id: id.to_string(),
-                    ..Default::default()
-                };
-
-                while let Some(inner) = scanner.peek_line() {
-                    if LineScanner::is_closing_tag(inner, id) {
-                        scanner.next_line();
-                        break;
+    fn parse_screen(&mut self, root: &Section) -> Result<ScreenConfig, ConfigError> {
+        let mut config = ScreenConfig::default();
+
+        for node in &root.children {
+            match node {
+                Node::Section(section) => match section.name.as_str() {
+                    AVAILABLE_RADIOSTATIONS => {
+                        config.available_radiostations = self.parse_children(
+                            section,
+                            AVAILABLE_RADIOSTATION,
+                            Self::parse_radiostation,
+                        )?
+                    }
+                    PHONE_PANELS => {
+                        config.phone_panels =
+                            self.parse_children(section, PANEL, Self::parse_phone_panel)?
                     }
-                    if let Some((key, value)) = LineScanner::parse_kv(inner) {
-                        scanner.next_line();
-                        match key {
-                            RADIO_NAME => station.radio_name = LineScanner::clean_string(value),
-                            SLOT => station.slot = value.parse()?,
-                            _ => {}
+                    RADIO_PANELS => {
+                        config.radio_panels =
+                            self.parse_children(section, PANEL, Self::parse_radio_panel)?
+                    }
+                    _ => self.unexpected_section(section)?,
+                },
+                Node::Entry(entry) => match entry.key.as_str() {
+                    INTERNAL_ADDRESS => {
+                        // Некорректный адрес остаётся незаданным, а не нулевым.
+                        if let Some(address) = self.parse_int(entry)? {
+                            config.internal_address = Some(address);
                         }
-                    } else {
-                        scanner.next_line();
                     }
-                }
-                stations.push(station);
-            } else {
-                scanner.next_line();
+                    NAME => config.name = Some(LineScanner::clean_string(entry.value())),
+                    key if IGNORED_GLOBAL_KEYS.contains(&key) => {}
+                    _ => {
+                        if self.options.strict {
+                            let key = entry.key.clone();
+                            let location = entry.location.clone();
+                            self.report(
+                                Severity::Error,
+                                ConfigError::UnknownGlobalKey { key, location },
+                            )?;
+                        }
+                    }
+                },
+                Node::Raw(raw) => self.unrecognized(raw)?,
             }
-        } else {
-            scanner.next_line();
         }
+        Ok(config)
+    }
+
+    fn parse_radiostation(
+        &mut self,
+        section: &Section,
+    ) -> Result<AvailableRadiostation, ConfigError> {
+        let mut station = AvailableRadiostation {
+            id: section.name.clone(),
+            ..Default::default()
+        };
+        self.parse_keys(section, &REQUIRED_RADIOSTATION_KEYS, |parser, entry| {
+            match entry.key.as_str() {
+                RADIO_NAME => station.radio_name = LineScanner::clean_string(entry.value()),
+                SLOT => parser.read_int(entry, &mut station.slot)?,
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+        Ok(station)
+    }
+
+    fn parse_phone_panel(&mut self, section: &Section) -> Result<PhonePanel, ConfigError> {
+        Ok(PhonePanel {
+            id: localize_panel_label(&section.name),
+            buttons: self.parse_children(section, BUTTON, Self::parse_phone_button)?,
+        })
+    }
+
+    fn parse_phone_button(&mut self, section: &Section) -> Result<PhoneButton, ConfigError> {
+        let mut btn = PhoneButton {
+            id: section.name.clone(),
+            ..Default::default()
+        };
+        self.parse_keys(section, &REQUIRED_PHONE_BUTTON_KEYS, |parser, entry| {
+            match entry.key.as_str() {
+                INTERNAL_ADDRESS => parser.read_int(entry, &mut btn.internal_address)?,
+                POSITION_X => parser.read_float(entry, &mut btn.position_x)?,
+                POSITION_Y => parser.read_float(entry, &mut btn.position_y)?,
+                SIZE_HEIGHT => parser.read_float(entry, &mut btn.size_height)?,
+                SIZE_WIDTH => parser.read_float(entry, &mut btn.size_width)?,
+                TEXT => btn.text = LineScanner::clean_string(entry.value()),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+        Ok(btn)
+    }
+
+    fn parse_radio_panel(&mut self, section: &Section) -> Result<RadioPanel, ConfigError> {
+        Ok(RadioPanel {
+            id: section.name.clone(),
+            buttons: self.parse_children(section, BUTTON, Self::parse_radio_button)?,
+        })
+    }
+
+    fn parse_radio_button(&mut self, section: &Section) -> Result<RadioButton, ConfigError> {
+        let mut btn = RadioButton {
+            id: section.name.clone(),
+            ..Default::default()
+        };
+        self.parse_keys(section, &REQUIRED_RADIO_BUTTON_KEYS, |parser, entry| {
+            match entry.key.as_str() {
+                POSITION_X => parser.read_float(entry, &mut btn.position_x)?,
+                POSITION_Y => parser.read_float(entry, &mut btn.position_y)?,
+                SIZE_HEIGHT => parser.read_float(entry, &mut btn.size_height)?,
+                SIZE_WIDTH => parser.read_float(entry, &mut btn.size_width)?,
+                SLOT => parser.read_int(entry, &mut btn.slot)?,
+                TEXT => btn.text = LineScanner::clean_string(entry.value()),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+        Ok(btn)
     }
-    Ok(stations)
 }
 
-fn localize_panel_label(value: &str) -> String {
-    if let Some(rest) = value.strip_prefix(PANEL) {
-        if rest.parse::<usize>().is_ok() {
-            return format!("ПД {}", rest.strip_prefix("0").unwrap_or_default());
-        }
+const LOCALIZED_PANEL: &str = "ПД ";
+
+/// Подпись телефонной панели в интерфейсе: `Panel01` -> `ПД 1`,
+/// `Panel10` -> `ПД 10`. Номер обязан однозначно переводиться обратно в имя
+/// секции ([`delocalize_panel_label`]), иначе при сохранении панели с
+/// двузначными номерами сливаются в одну.
+pub(crate) fn localize_panel_label(value: &str) -> String {
+    if let Some(rest) = value.strip_prefix(PANEL)
+        && let Ok(number) = rest.parse::<usize>()
+    {
+        return format!("{}{}", LOCALIZED_PANEL, number);
+    }
+    value.to_string()
+}
+
+pub(crate) fn delocalize_panel_label(value: &str) -> String {
+    if let Some(rest) = value.strip_prefix(LOCALIZED_PANEL)
+        && let Ok(number) = rest.parse::<usize>()
+    {
+        return format!("{}{:02}", PANEL, number);
     }
     value.to_string()
 }
 
-fn parse_phone_panels(scanner: &mut LineScanner) -> Result<Vec<PhonePanel>, ConfigError> {
-    let mut panels = Vec::new();
-    while let Some(line) = scanner.peek_line() {
-        if LineScanner::is_closing_tag(line, PHONE_PANELS) {
-            scanner.next_line();
-            break;
+// -----------------------------------------------------------------------------
+// loading from bytes and files
+// -----------------------------------------------------------------------------
+
+/// Конфигурация, загруженная из файла, вместе с декодированным текстом и
+/// определённой кодировкой.
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: ScreenConfig,
+    pub text: String,
+    pub encoding: TextEncoding,
+    pub invalid_offsets: Vec<usize>,
+}
+
+#[derive(Error, Debug)]
+pub enum LoadError {
+    #[error("Failed to read config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Charset(#[from] CharsetError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+/// Загружает конфигурацию из байтов, определяя кодировку (KOI8-R, CP1251 или
+/// UTF-8). Недопустимый для выбранной кодировки байт считается ошибкой.
+pub fn load_bytes(bytes: &[u8]) -> Result<LoadedConfig, LoadError> {
+    let decoded = charset::decode_auto(bytes)?;
+    let config = parse(&decoded.text)?;
+    Ok(LoadedConfig {
+        config,
+        text: decoded.text,
+        encoding: decoded.encoding,
+        invalid_offsets: decoded.invalid_offsets,
+    })
+}
+
+/// То же, что [`load_bytes`], но недопустимые байты заменяются на `U+FFFD`,
+/// а их смещения попадают в [`LoadedConfig::invalid_offsets`].
+pub fn load_bytes_lossy(bytes: &[u8]) -> Result<LoadedConfig, LoadError> {
+    let decoded = charset::decode_auto_lossy(bytes);
+    let config = parse(&decoded.text)?;
+    Ok(LoadedConfig {
+        config,
+        text: decoded.text,
+        encoding: decoded.encoding,
+        invalid_offsets: decoded.invalid_offsets,
+    })
+}
+
+pub fn load_path(path: impl AsRef<std::path::Path>) -> Result<LoadedConfig, LoadError> {
+    load_bytes(&std::fs::read(path)?)
+}
+
+// -----------------------------------------------------------------------------
+// encoding for saving
+// -----------------------------------------------------------------------------
+
+/// Символ конфигурации, которого нет в целевой кодировке, и его положение,
+/// например `PhonePanels/Panel02/Button01/text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodingIssue {
+    pub ch: char,
+    pub replacement: Option<&'static str>,
+    pub location: Location,
+}
+
+impl std::fmt::Display for EncodingIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} (U+{:04X}) at {}",
+            self.ch, self.ch as u32, self.location
+        )?;
+        if let Some(replacement) = self.replacement {
+            write!(f, " replaced with {:?}", replacement)?;
         }
+        Ok(())
+    }
+}
 
-        if let Some(panel_id) = LineScanner::get_section_name(line) {
-            if panel_id.starts_with(PANEL) {
-                scanner.next_line();
-                let mut panel = PhonePanel {
-                    id: localize_panel_label(panel_id),
-                    buttons: vec![],
-                };
-
-                while let Some(inner) = scanner.peek_line() {
-                    if LineScanner::is_closing_tag(inner, panel_id) {
-                        scanner.next_line();
-                        break;
-                    }
+#[derive(Error, Debug, Clone)]
+pub enum EncodeError {
+    #[error("{} character(s) cannot be written in {encoding}", issues.len())]
+    Unrepresentable {
+        encoding: TextEncoding,
+        issues: Vec<EncodingIssue>,
+    },
+}
 
-                    if let Some(btn_id) = LineScanner::get_section_name(inner) {
-                        if btn_id.starts_with(BUTTON) {
-                            scanner.next_line();
-                            let mut btn = PhoneButton {
-                                id: btn_id.to_string(),
-                                ..Default::default()
-                            };
-                            while let Some(b_line) = scanner.peek_line() {
-                                if LineScanner::is_closing_tag(b_line, btn_id) {
-                                    scanner.next_line();
-                                    break;
-                                }
-                                if let Some((k, v)) = LineScanner::parse_kv(b_line) {
-                                    scanner.next_line();
-                                    match k {
-                                        INTERNAL_ADDRESS => btn.internal_address = v.parse()?,
-                                        POSITION_X => btn.position_x = v.parse()?,
-                                        POSITION_Y => btn.position_y = v.parse()?,
-                                        SIZE_HEIGHT => btn.size_height = v.parse()?,
-                                        SIZE_WIDTH => btn.size_width = v.parse()?,
-                                        TEXT => btn.text = LineScanner::clean_string(v),
-                                        _ => {}
-                                    }
-                                } else {
-                                    scanner.next_line();
-                                }
-                            }
-                            panel.buttons.push(btn);
-                        } else {
-                            scanner.next_line();
-                        }
-                    } else {
-                        scanner.next_line();
-                    }
-                }
-                panels.push(panel);
-            } else {
-                scanner.next_line();
-            }
-        } else {
-            scanner.next_line();
+/// Байты конфигурации для записи на диск и сделанные при этом замены.
+#[derive(Debug, Clone)]
+pub struct EncodedConfig {
+    pub bytes: Vec<u8>,
+    pub substitutions: Vec<EncodingIssue>,
+}
+
+/// Кодирует текст конфигурации для сохранения (по умолчанию пульт ожидает
+/// KOI8-R). При [`SubstitutionPolicy::Strict`] любой непредставимый символ
+/// приводит к ошибке со списком всех таких символов и их положений.
+pub fn encode_config(
+    text: &str,
+    encoding: TextEncoding,
+    policy: SubstitutionPolicy,
+) -> Result<EncodedConfig, EncodeError> {
+    let encoded = charset::encode(text, encoding, policy);
+    let locations = locate_offsets(text, encoded.unrepresentable.iter().map(|u| u.offset));
+    let issues: Vec<EncodingIssue> = encoded
+        .unrepresentable
+        .iter()
+        .zip(locations)
+        .map(|(u, location)| EncodingIssue {
+            ch: u.ch,
+            replacement: u.replacement,
+            location,
+        })
+        .collect();
+
+    if policy == SubstitutionPolicy::Strict && !issues.is_empty() {
+        return Err(EncodeError::Unrepresentable { encoding, issues });
+    }
+    Ok(EncodedConfig {
+        bytes: encoded.bytes,
+        substitutions: issues,
+    })
+}
+
+/// Переводит байтовые смещения в тексте в положения с путём секций.
+fn locate_offsets(text: &str, offsets: impl Iterator<Item = usize>) -> Vec<Location> {
+    let mut offsets = offsets.peekable();
+    let mut locations = Vec::new();
+    let mut scanner = LineScanner::new(text);
+    let text_start = text.as_ptr() as usize;
+
+    // Строки берутся целиком: символ в пробелах по краям строки (например,
+    // неразрывный пробел в конце) или в пробельной строке иначе попал бы в
+    // следующую строку или пропал бы совсем.
+    while offsets.peek().is_some()
+        && let Some(line) = scanner.next_raw_line()
+    {
+        let line_end = line.as_ptr() as usize - text_start + line.len();
+        let key = LineScanner::parse_kv(line.trim()).map_or("", |(key, _)| key);
+        while let Some(&offset) = offsets.peek()
+            && offset < line_end
+        {
+            locations.push(scanner.location(key, &text[offset..line_end]));
+            offsets.next();
         }
     }
-    Ok(panels)
+    locations
+}
+
+// -----------------------------------------------------------------------------
+// serialization logic
+// -----------------------------------------------------------------------------
+
+/// Формирует текст конфигурации в формате `.conf`, который принимает пульт.
+///
+/// Глобальные ключи, секции и ключи кнопок выводятся в том же порядке, в каком
+/// их записывает сам пульт: по алфавиту, без отступов.
+pub fn serialize(config: &ScreenConfig) -> String {
+    let mut writer = LineWriter::new();
+
+    if let Some(address) = config.internal_address {
+        writer.write_kv(INTERNAL_ADDRESS, address);
+    }
+    if let Some(name) = &config.name {
+        writer.write_kv(NAME, LineWriter::quote_string(name));
+    }
+
+    write_radiostations(&mut writer, &config.available_radiostations);
+    write_phone_panels(&mut writer, &config.phone_panels);
+    write_radio_panels(&mut writer, &config.radio_panels);
+
+    writer.finish()
+}
+
+fn write_radiostations(writer: &mut LineWriter, stations: &[AvailableRadiostation]) {
+    writer.open_section(AVAILABLE_RADIOSTATIONS);
+    for station in stations {
+        writer.open_section(&station.id);
+        writer.write_kv(RADIO_NAME, LineWriter::quote_string(&station.radio_name));
+        writer.write_kv(SLOT, station.slot);
+        writer.close_section(&station.id);
+    }
+    writer.close_section(AVAILABLE_RADIOSTATIONS);
 }
 
-fn parse_radio_panels(scanner: &mut LineScanner) -> Result<Vec<RadioPanel>, ConfigError> {
-    let mut panels = Vec::new();
-    while let Some(line) = scanner.peek_line() {
-        if LineScanner::is_closing_tag(line, RADIO_PANELS) {
-            scanner.next_line();
-            break;
+fn write_phone_panels(writer: &mut LineWriter, panels: &[PhonePanel]) {
+    writer.open_section(PHONE_PANELS);
+    for panel in panels {
+        let panel_id = delocalize_panel_label(&panel.id);
+        writer.open_section(&panel_id);
+        for btn in &panel.buttons {
+            writer.open_section(&btn.id);
+            writer.write_kv(INTERNAL_ADDRESS, btn.internal_address);
+            writer.write_kv(POSITION_X, btn.position_x);
+            writer.write_kv(POSITION_Y, btn.position_y);
+            writer.write_kv(SIZE_HEIGHT, btn.size_height);
+            writer.write_kv(SIZE_WIDTH, btn.size_width);
+            writer.write_kv(TEXT, LineWriter::quote_string(&btn.text));
+            writer.close_section(&btn.id);
         }
+        writer.close_section(&panel_id);
+    }
+    writer.close_section(PHONE_PANELS);
+}
 
-        if let Some(panel_id) = LineScanner::get_section_name(line) {
-            if panel_id.starts_with(PANEL) {
-                scanner.next_line();
-                let mut panel = RadioPanel {
-                    id: panel_id.to_string(),
-                    buttons: vec![],
-                };
-
-                while let Some(inner) = scanner.peek_line() {
-                    if LineScanner::is_closing_tag(inner, panel_id) {
-                        scanner.next_line();
-                        break;
-                    }
+fn write_radio_panels(writer: &mut LineWriter, panels: &[RadioPanel]) {
+    writer.open_section(RADIO_PANELS);
+    for panel in panels {
+        writer.open_section(&panel.id);
+        for btn in &panel.buttons {
+            writer.open_section(&btn.id);
+            writer.write_kv(POSITION_X, btn.position_x);
+            writer.write_kv(POSITION_Y, btn.position_y);
+            writer.write_kv(SIZE_HEIGHT, btn.size_height);
+            writer.write_kv(SIZE_WIDTH, btn.size_width);
+            writer.write_kv(SLOT, btn.slot);
+            writer.write_kv(TEXT, LineWriter::quote_string(&btn.text));
+            writer.close_section(&btn.id);
+        }
+        writer.close_section(&panel.id);
+    }
+    writer.close_section(RADIO_PANELS);
+}
 
-                    if let Some(btn_id) = LineScanner::get_section_name(inner) {
-                        if btn_id.starts_with(BUTTON) {
-                            scanner.next_line();
-                            let mut btn = RadioButton {
-                                id: btn_id.to_string(),
-                                ..Default::default()
-                            };
-                            while let Some(b_line) = scanner.peek_line() {
-                                if LineScanner::is_closing_tag(b_line, btn_id) {
-                                    scanner.next_line();
-                                    break;
-                                }
-                                if let Some((key, value)) = LineScanner::parse_kv(b_line) {
-                                    scanner.next_line();
-                                    match key {
-                                        POSITION_X => btn.position_x = value.parse()?,
-                                        POSITION_Y => btn.position_y = value.parse()?,
-                                        SIZE_HEIGHT => btn.size_height = value.parse()?,
-                                        SIZE_WIDTH => btn.size_width = value.parse()?,
-                                        SLOT => btn.slot = value.parse()?,
-                                        TEXT => btn.text = LineScanner::clean_string(value),
-                                        _ => {}
-                                    }
-                                } else {
-                                    scanner.next_line();
-                                }
-                            }
-                            panel.buttons.push(btn);
-                        } else {
-                            scanner.next_line();
-                        }
-                    } else {
-                        scanner.next_line();
-                    }
-                }
-                panels.push(panel);
-            } else {
-                scanner.next_line();
+// -----------------------------------------------------------------------------
+// document synchronization
+// -----------------------------------------------------------------------------
+
+/// Переносит изменения из `config` в исходное дерево документа.
+///
+/// Переписываются только строки, значения которых действительно изменились;
+/// неизвестные ключи и секции, запись чисел, отступы и порядок строк остаются
+/// такими же, как в загруженном файле.
+pub fn update_document(doc: &mut Document, config: &ScreenConfig) {
+    let eol = doc.line_ending().to_string();
+    let root = &mut doc.root;
+
+    // Адрес, который не удалось разобрать, не считается удалённым: строка
+    // остаётся в файле, пока адрес не задан заново.
+    let address = config.internal_address.map(|a| FieldValue::Int(a.into()));
+    let unreadable = root
+        .entry(INTERNAL_ADDRESS)
+        .is_some_and(|entry| entry.value().parse::<u32>().is_err());
+    if address.is_some() || !unreadable {
+        sync_field(root, INTERNAL_ADDRESS, address, &eol);
+    }
+    sync_field(
+        root,
+        NAME,
+        config.name.as_deref().map(FieldValue::Text),
+        &eol,
+    );
+
+    sync_child_section(
+        root,
+        AVAILABLE_RADIOSTATIONS,
+        !config.available_radiostations.is_empty(),
+        &eol,
+        |section| {
+            sync_items(
+                section,
+                &config.available_radiostations,
+                |name| name.starts_with(AVAILABLE_RADIOSTATION),
+                |name, station| name == station.id,
+                |station| station.id.clone(),
+                sync_radiostation,
+                &eol,
+            )
+        },
+    );
+    sync_child_section(
+        root,
+        PHONE_PANELS,
+        !config.phone_panels.is_empty(),
+        &eol,
+        |section| {
+            sync_items(
+                section,
+                &config.phone_panels,
+                |name| name.starts_with(PANEL),
+                |name, panel| localize_panel_label(name) == panel.id,
+                |panel| delocalize_panel_label(&panel.id),
+                sync_phone_panel,
+                &eol,
+            )
+        },
+    );
+    sync_child_section(
+        root,
+        RADIO_PANELS,
+        !config.radio_panels.is_empty(),
+        &eol,
+        |section| {
+            sync_items(
+                section,
+                &config.radio_panels,
+                |name| name.starts_with(PANEL),
+                |name, panel| name == panel.id,
+                |panel| panel.id.clone(),
+                sync_radio_panel,
+                &eol,
+            )
+        },
+    );
+}
+
+enum FieldValue<'a> {
+    Int(i64),
+    Float(f32),
+    Text(&'a str),
+}
+
+impl FieldValue<'_> {
+    fn matches(&self, raw: &str) -> bool {
+        match self {
+            FieldValue::Int(v) => raw.parse::<i64>() == Ok(*v),
+            FieldValue::Float(v) => raw.parse::<f32>() == Ok(*v),
+            FieldValue::Text(v) => LineScanner::clean_string(raw) == *v,
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            FieldValue::Int(v) => v.to_string(),
+            FieldValue::Float(v) => v.to_string(),
+            FieldValue::Text(v) => LineWriter::quote_string(v),
+        }
+    }
+}
+
+fn sync_field(section: &mut Section, key: &str, value: Option<FieldValue>, eol: &str) {
+    match (value, section.entry_mut(key)) {
+        (Some(value), Some(entry)) => {
+            if !value.matches(entry.value()) {
+                entry.set_value(&value.render());
             }
-        } else {
-            scanner.next_line();
+        }
+        (Some(value), None) => section.insert_entry(Entry::new(key, &value.render(), eol)),
+        (None, _) => {
+            section.remove_entry(key);
         }
     }
-    Ok(panels)
 }
 
-fn consume_block(scanner: &mut LineScanner, block_name: &str) {
-    while let Some(line) = scanner.next_line() {
-        if LineScanner::is_closing_tag(line, block_name) {
-            break;
+/// Синхронизирует дочернюю секцию. Отсутствующая секция создаётся, только
+/// если в неё есть что записать: файл без, например, `[RadioPanels]` не
+/// должен обрастать пустыми секциями при любой правке.
+fn sync_child_section(
+    parent: &mut Section,
+    name: &str,
+    has_items: bool,
+    eol: &str,
+    sync: impl FnOnce(&mut Section),
+) {
+    if parent.section(name).is_none() {
+        if !has_items {
+            return;
         }
+        parent.insert_section(parent.children.len(), Section::new(name, eol));
+    }
+    if let Some(section) = parent.section_mut(name) {
+        sync(section);
     }
 }
+
+fn sync_items<T>(
+    parent: &mut Section,
+    items: &[T],
+    is_item: impl Fn(&str) -> bool,
+    matches: impl Fn(&str, &T) -> bool,
+    section_name: impl Fn(&T) -> String,
+    sync: impl Fn(&mut Section, &T, &str),
+    eol: &str,
+) {
+    let is_item_node = |node: &Node| matches!(node, Node::Section(s) if is_item(&s.name));
+
+    parent.children.retain(|node| match node {
+        Node::Section(s) if is_item(&s.name) => items.iter().any(|item| matches(&s.name, item)),
+        _ => true,
+    });
+
+    // Если порядок элементов в модели изменился, переставляем секции целиком,
+    // начиная с позиции первой из них.
+    let order: Vec<usize> = parent
+        .sections()
+        .filter(|s| is_item(&s.name))
+        .filter_map(|s| items.iter().position(|item| matches(&s.name, item)))
+        .collect();
+    if !order.is_sorted() {
+        let first = parent.children.iter().position(is_item_node).unwrap_or(0);
+        let mut taken: Vec<Section> = Vec::new();
+        parent.children.retain(|node| match node {
+            Node::Section(s) if is_item(&s.name) => {
+                taken.push(s.clone());
+                false
+            }
+            _ => true,
+        });
+        taken.sort_by_key(|s| items.iter().position(|item| matches(&s.name, item)));
+        for (offset, section) in taken.into_iter().enumerate() {
+            parent.insert_section(first + offset, section);
+        }
+    }
+
+    let mut insert_at = parent
+        .children
+        .iter()
+        .position(is_item_node)
+        .unwrap_or(parent.children.len());
+    for item in items {
+        let existing = parent.children[insert_at..].iter().position(
+            |node| matches!(node, Node::Section(s) if is_item(&s.name) && matches(&s.name, item)),
+        );
+        match existing {
+            Some(offset) => {
+                if let Node::Section(section) = &mut parent.children[insert_at + offset] {
+                    sync(section, item, eol);
+                }
+                insert_at += offset + 1;
+            }
+            None => {
+                let mut section = Section::new(&section_name(item), eol);
+                sync(&mut section, item, eol);
+                parent.insert_section(insert_at, section);
+                insert_at += 1;
+            }
+        }
+    }
+}
+
+fn sync_radiostation(section: &mut Section, station: &AvailableRadiostation, eol: &str) {
+    sync_field(
+        section,
+        RADIO_NAME,
+        Some(FieldValue::Text(&station.radio_name)),
+        eol,
+    );
+    sync_field(
+        section,
+        SLOT,
+        Some(FieldValue::Int(station.slot.into())),
+        eol,
+    );
+}
+
+fn sync_phone_panel(section: &mut Section, panel: &PhonePanel, eol: &str) {
+    sync_items(
+        section,
+        &panel.buttons,
+        |name| name.starts_with(BUTTON),
+        |name, btn| name == btn.id,
+        |btn| btn.id.clone(),
+        sync_phone_button,
+        eol,
+    );
+}
+
+fn sync_phone_button(section: &mut Section, btn: &PhoneButton, eol: &str) {
+    let address = FieldValue::Int(btn.internal_address.into());
+    sync_field(section, INTERNAL_ADDRESS, Some(address), eol);
+    sync_field(
+        section,
+        POSITION_X,
+        Some(FieldValue::Float(btn.position_x)),
+        eol,
+    );
+    sync_field(
+        section,
+        POSITION_Y,
+        Some(FieldValue::Float(btn.position_y)),
+        eol,
+    );
+    sync_field(
+        section,
+        SIZE_HEIGHT,
+        Some(FieldValue::Float(btn.size_height)),
+        eol,
+    );
+    sync_field(
+        section,
+        SIZE_WIDTH,
+        Some(FieldValue::Float(btn.size_width)),
+        eol,
+    );
+    sync_field(section, TEXT, Some(FieldValue::Text(&btn.text)), eol);
+}
+
+fn sync_radio_panel(section: &mut Section, panel: &RadioPanel, eol: &str) {
+    sync_items(
+        section,
+        &panel.buttons,
+        |name| name.starts_with(BUTTON),
+        |name, btn| name == btn.id,
+        |btn| btn.id.clone(),
+        sync_radio_button,
+        eol,
+    );
+}
+
+fn sync_radio_button(section: &mut Section, btn: &RadioButton, eol: &str) {
+    sync_field(
+        section,
+        POSITION_X,
+        Some(FieldValue::Float(btn.position_x)),
+        eol,
+    );
+    sync_field(
+        section,
+        POSITION_Y,
+        Some(FieldValue::Float(btn.position_y)),
+        eol,
+    );
+    sync_field(
+        section,
+        SIZE_HEIGHT,
+        Some(FieldValue::Float(btn.size_height)),
+        eol,
+    );
+    sync_field(
+        section,
+        SIZE_WIDTH,
+        Some(FieldValue::Float(btn.size_width)),
+        eol,
+    );
+    sync_field(section, SLOT, Some(FieldValue::Int(btn.slot.into())), eol);
+    sync_field(section, TEXT, Some(FieldValue::Text(&btn.text)), eol);
+}
@@ -0,0 +1,476 @@
+use crate::config::{PhonePanel, RadioPanel, ScreenConfig};
+use crate::lint::{PanelKind, Rect};
+
+// Coordinates are written with three decimals; anything below that is noise.
+const COORD_EPSILON: f32 = 1e-4;
+
+// -----------------------------------------------------------------------------
+// diff data structures
+// -----------------------------------------------------------------------------
+
+/// Куда ведёт кнопка: внутренний номер абонента для телефонной панели или
+/// слот радиостанции для радиопанели.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Address(u32),
+    Slot(i32),
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Address(address) => write!(f, "address {}", address),
+            Target::Slot(slot) => write!(f, "slot {}", slot),
+        }
+    }
+}
+
+/// Кнопка в виде, удобном для сравнения.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ButtonInfo {
+    pub id: String,
+    pub text: String,
+    pub target: Target,
+    pub rect: Rect,
+}
+
+impl std::fmt::Display for ButtonInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:?} {} at {}",
+            self.id,
+            self.text,
+            self.target,
+            format_rect(&self.rect)
+        )
+    }
+}
+
+fn format_rect(rect: &Rect) -> String {
+    format!(
+        "({:.3}, {:.3}) {:.3}x{:.3}",
+        rect.x, rect.y, rect.width, rect.height
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ButtonChange {
+    Added(ButtonInfo),
+    Removed(ButtonInfo),
+    /// Кнопка сохранилась, но изменились её положение, размер, адрес или
+    /// подпись. Смена одного лишь номера `ButtonNN` изменением не считается.
+    Changed {
+        old: ButtonInfo,
+        new: ButtonInfo,
+    },
+}
+
+impl ButtonChange {
+    fn changed(old: ButtonInfo, new: ButtonInfo) -> Option<Self> {
+        let change = ButtonChange::Changed { old, new };
+        (change.moved() || change.resized() || change.retargeted() || change.relabelled())
+            .then_some(change)
+    }
+
+    fn pair(&self) -> Option<(&ButtonInfo, &ButtonInfo)> {
+        match self {
+            ButtonChange::Changed { old, new } => Some((old, new)),
+            _ => None,
+        }
+    }
+
+    pub fn moved(&self) -> bool {
+        self.pair().is_some_and(|(old, new)| {
+            !same_coord(old.rect.x, new.rect.x) || !same_coord(old.rect.y, new.rect.y)
+        })
+    }
+
+    pub fn resized(&self) -> bool {
+        self.pair().is_some_and(|(old, new)| {
+            !same_coord(old.rect.width, new.rect.width)
+                || !same_coord(old.rect.height, new.rect.height)
+        })
+    }
+
+    pub fn retargeted(&self) -> bool {
+        self.pair()
+            .is_some_and(|(old, new)| old.target != new.target)
+    }
+
+    pub fn relabelled(&self) -> bool {
+        self.pair().is_some_and(|(old, new)| old.text != new.text)
+    }
+}
+
+impl std::fmt::Display for ButtonChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ButtonChange::Added(button) => write!(f, "+ {}", button),
+            ButtonChange::Removed(button) => write!(f, "- {}", button),
+            ButtonChange::Changed { old, new } => {
+                let mut details = Vec::new();
+                if self.moved() {
+                    details.push(format!(
+                        "moved ({:.3}, {:.3}) -> ({:.3}, {:.3})",
+                        old.rect.x, old.rect.y, new.rect.x, new.rect.y
+                    ));
+                }
+                if self.resized() {
+                    details.push(format!(
+                        "resized {:.3}x{:.3} -> {:.3}x{:.3}",
+                        old.rect.width, old.rect.height, new.rect.width, new.rect.height
+                    ));
+                }
+                if self.retargeted() {
+                    details.push(format!("{} -> {}", old.target, new.target));
+                }
+                if self.relabelled() {
+                    details.push(format!("label {:?} -> {:?}", old.text, new.text));
+                }
+                let id = if old.id == new.id {
+                    old.id.clone()
+                } else {
+                    format!("{} -> {}", old.id, new.id)
+                };
+                write!(f, "~ {} {:?}: {}", id, new.text, details.join("; "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelStatus {
+    Added,
+    Removed,
+    Kept,
+}
+
+/// Изменения одной панели. Для добавленной или удалённой панели все её
+/// кнопки перечислены как добавленные или удалённые.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PanelDiff {
+    pub kind: PanelKind,
+    pub panel: String,
+    pub status: PanelStatus,
+    pub buttons: Vec<ButtonChange>,
+}
+
+impl std::fmt::Display for PanelDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.status {
+            PanelStatus::Added => write!(
+                f,
+                "+ {} {} ({} buttons)",
+                self.kind,
+                self.panel,
+                self.buttons.len()
+            ),
+            PanelStatus::Removed => write!(
+                f,
+                "- {} {} ({} buttons)",
+                self.kind,
+                self.panel,
+                self.buttons.len()
+            ),
+            PanelStatus::Kept => {
+                write!(f, "{} {}:", self.kind, self.panel)?;
+                for change in &self.buttons {
+                    write!(f, "\n  {}", change)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StationChange {
+    Added { name: String, slot: i32 },
+    Removed { name: String, slot: i32 },
+    SlotChanged { name: String, from: i32, to: i32 },
+}
+
+impl std::fmt::Display for StationChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StationChange::Added { name, slot } => {
+                write!(f, "+ radiostation {:?} (slot {})", name, slot)
+            }
+            StationChange::Removed { name, slot } => {
+                write!(f, "- radiostation {:?} (slot {})", name, slot)
+            }
+            StationChange::SlotChanged { name, from, to } => {
+                write!(f, "~ radiostation {:?}: slot {} -> {}", name, from, to)
+            }
+        }
+    }
+}
+
+/// Изменение глобального поля рабочего места (`name`, `internal_address`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl std::fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let show = |value: &Option<String>| value.clone().unwrap_or_else(|| "(none)".to_string());
+        write!(
+            f,
+            "~ {}: {} -> {}",
+            self.field,
+            show(&self.from),
+            show(&self.to)
+        )
+    }
+}
+
+/// Структурные различия двух конфигураций.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigDiff {
+    pub fields: Vec<FieldChange>,
+    pub radiostations: Vec<StationChange>,
+    pub panels: Vec<PanelDiff>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.radiostations.is_empty() && self.panels.is_empty()
+    }
+
+    pub fn panel(&self, kind: PanelKind, panel: &str) -> Option<&PanelDiff> {
+        self.panels
+            .iter()
+            .find(|diff| diff.kind == kind && diff.panel == panel)
+    }
+}
+
+impl std::fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in &self.fields {
+            writeln!(f, "{}", change)?;
+        }
+        for change in &self.radiostations {
+            writeln!(f, "{}", change)?;
+        }
+        for panel in &self.panels {
+            writeln!(f, "{}", panel)?;
+        }
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// diff logic
+// -----------------------------------------------------------------------------
+
+pub(crate) fn same_coord(a: f32, b: f32) -> bool {
+    (a - b).abs() < COORD_EPSILON
+}
+
+pub(crate) fn phone_buttons(panel: &PhonePanel) -> Vec<ButtonInfo> {
+    panel
+        .buttons
+        .iter()
+        .map(|b| ButtonInfo {
+            id: b.id.clone(),
+            text: b.text.clone(),
+            target: Target::Address(b.internal_address),
+            rect: Rect::from(b),
+        })
+        .collect()
+}
+
+pub(crate) fn radio_buttons(panel: &RadioPanel) -> Vec<ButtonInfo> {
+    panel
+        .buttons
+        .iter()
+        .map(|b| ButtonInfo {
+            id: b.id.clone(),
+            text: b.text.clone(),
+            target: Target::Slot(b.slot),
+            rect: Rect::from(b),
+        })
+        .collect()
+}
+
+/// Сравнивает две конфигурации. Панели сопоставляются по идентификатору, а
+/// кнопки внутри панели - по адресу (слоту) и подписи, так что перенумерация
+/// `ButtonNN` не даёт ложных изменений. Кнопки, у которых совпадает только
+/// адрес, только подпись или только положение, считаются изменёнными.
+pub fn diff(old: &ScreenConfig, new: &ScreenConfig) -> ConfigDiff {
+    let mut result = ConfigDiff::default();
+
+    if old.name != new.name {
+        result.fields.push(FieldChange {
+            field: "name",
+            from: old.name.clone(),
+            to: new.name.clone(),
+        });
+    }
+    if old.internal_address != new.internal_address {
+        result.fields.push(FieldChange {
+            field: "internal_address",
+            from: old.internal_address.map(|a| a.to_string()),
+            to: new.internal_address.map(|a| a.to_string()),
+        });
+    }
+
+    for station in &old.available_radiostations {
+        match new
+            .available_radiostations
+            .iter()
+            .find(|s| s.radio_name == station.radio_name)
+        {
+            None => result.radiostations.push(StationChange::Removed {
+                name: station.radio_name.clone(),
+                slot: station.slot,
+            }),
+            Some(other) if other.slot != station.slot => {
+                result.radiostations.push(StationChange::SlotChanged {
+                    name: station.radio_name.clone(),
+                    from: station.slot,
+                    to: other.slot,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for station in &new.available_radiostations {
+        if !old
+            .available_radiostations
+            .iter()
+            .any(|s| s.radio_name == station.radio_name)
+        {
+            result.radiostations.push(StationChange::Added {
+                name: station.radio_name.clone(),
+                slot: station.slot,
+            });
+        }
+    }
+
+    let old_phone: Vec<_> = old
+        .phone_panels
+        .iter()
+        .map(|p| (p.id.as_str(), phone_buttons(p)))
+        .collect();
+    let new_phone: Vec<_> = new
+        .phone_panels
+        .iter()
+        .map(|p| (p.id.as_str(), phone_buttons(p)))
+        .collect();
+    diff_panels(PanelKind::Phone, &old_phone, &new_phone, &mut result.panels);
+
+    let old_radio: Vec<_> = old
+        .radio_panels
+        .iter()
+        .map(|p| (p.id.as_str(), radio_buttons(p)))
+        .collect();
+    let new_radio: Vec<_> = new
+        .radio_panels
+        .iter()
+        .map(|p| (p.id.as_str(), radio_buttons(p)))
+        .collect();
+    diff_panels(PanelKind::Radio, &old_radio, &new_radio, &mut result.panels);
+
+    result
+}
+
+fn diff_panels(
+    kind: PanelKind,
+    old: &[(&str, Vec<ButtonInfo>)],
+    new: &[(&str, Vec<ButtonInfo>)],
+    out: &mut Vec<PanelDiff>,
+) {
+    for (id, old_buttons) in old {
+        let status;
+        let buttons = match new.iter().find(|(other, _)| other == id) {
+            Some((_, new_buttons)) => {
+                status = PanelStatus::Kept;
+                diff_buttons(old_buttons, new_buttons)
+            }
+            None => {
+                status = PanelStatus::Removed;
+                old_buttons
+                    .iter()
+                    .cloned()
+                    .map(ButtonChange::Removed)
+                    .collect()
+            }
+        };
+        if status != PanelStatus::Kept || !buttons.is_empty() {
+            out.push(PanelDiff {
+                kind,
+                panel: id.to_string(),
+                status,
+                buttons,
+            });
+        }
+    }
+    for (id, new_buttons) in new {
+        if !old.iter().any(|(other, _)| other == id) {
+            out.push(PanelDiff {
+                kind,
+                panel: id.to_string(),
+                status: PanelStatus::Added,
+                buttons: new_buttons
+                    .iter()
+                    .cloned()
+                    .map(ButtonChange::Added)
+                    .collect(),
+            });
+        }
+    }
+}
+
+/// Сопоставляет кнопки в несколько проходов: сначала по адресу и подписи,
+/// затем только по адресу, только по подписи, по идентификатору `ButtonNN`
+/// и лишь в последнюю очередь по положению - иначе кнопка, вставленная на
+/// место изменённой, "забирает" её сопоставление. Пустая подпись (обычная для
+/// радиокнопок) ничего не говорит о кнопке, и по ней одной пары не строятся.
+/// Возвращает пары индексов `(old, new)`.
+pub(crate) fn match_buttons(old: &[ButtonInfo], new: &[ButtonInfo]) -> Vec<(usize, usize)> {
+    let passes: [fn(&ButtonInfo, &ButtonInfo) -> bool; 5] = [
+        |a, b| a.target == b.target && a.text == b.text,
+        |a, b| a.target == b.target,
+        |a, b| !a.text.trim().is_empty() && a.text == b.text,
+        |a, b| a.id == b.id,
+        |a, b| same_coord(a.rect.x, b.rect.x) && same_coord(a.rect.y, b.rect.y),
+    ];
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    for matches in passes {
+        for (i, a) in old.iter().enumerate() {
+            if pairs.iter().any(|&(oi, _)| oi == i) {
+                continue;
+            }
+            let found = new
+                .iter()
+                .enumerate()
+                .find(|&(j, b)| !pairs.iter().any(|&(_, nj)| nj == j) && matches(a, b));
+            if let Some((j, _)) = found {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+fn diff_buttons(old: &[ButtonInfo], new: &[ButtonInfo]) -> Vec<ButtonChange> {
+    let pairs = match_buttons(old, new);
+    let mut changes = Vec::new();
+    for (i, a) in old.iter().enumerate() {
+        match pairs.iter().find(|&&(oi, _)| oi == i) {
+            Some(&(_, j)) => changes.extend(ButtonChange::changed(a.clone(), new[j].clone())),
+            None => changes.push(ButtonChange::Removed(a.clone())),
+        }
+    }
+    for (j, b) in new.iter().enumerate() {
+        if !pairs.iter().any(|&(_, nj)| nj == j) {
+            changes.push(ButtonChange::Added(b.clone()));
+        }
+    }
+    changes
+}
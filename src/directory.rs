@@ -0,0 +1,274 @@
+use crate::charset::{self, CharsetError};
+use crate::config::{ScreenConfig, Severity};
+use crate::lint::{ButtonRef, PanelKind};
+use crate::rules::{Finding, Rule};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+// -----------------------------------------------------------------------------
+// subscriber directory
+// -----------------------------------------------------------------------------
+
+/// Запись справочника абонентов.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Subscriber {
+    pub address: u32,
+    pub name: String,
+    #[serde(default)]
+    pub position: String,
+    #[serde(default)]
+    pub department: String,
+}
+
+impl std::fmt::Display for Subscriber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.address, self.name)?;
+        let details: Vec<&str> = [self.position.as_str(), self.department.as_str()]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect();
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DirectoryError {
+    #[error("Failed to read subscriber directory: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Charset(#[from] CharsetError),
+    #[error("Invalid subscriber directory at line {line}: {message}")]
+    Csv { line: u64, message: String },
+    #[error("Address {address} at line {line} is already listed in the directory")]
+    DuplicateAddress { address: u32, line: u64 },
+}
+
+/// Справочник абонентов площадки, импортированный из CSV.
+///
+/// Первая строка файла - заголовок с колонками `address`, `name`, `position`,
+/// `department` (две последние необязательны). Разделитель - запятая или
+/// точка с запятой, как при выгрузке из Excel; кодировка определяется так же,
+/// как у файлов конфигурации.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Directory {
+    subscribers: BTreeMap<u32, Subscriber>,
+    // words of each subscriber's name and position, see `tokens`
+    words: BTreeMap<u32, Vec<String>>,
+    // subscribers using each word
+    word_index: BTreeMap<String, Vec<u32>>,
+}
+
+impl Directory {
+    pub fn from_csv(input: &str) -> Result<Self, DirectoryError> {
+        let header = input.lines().next().unwrap_or_default();
+        let delimiter = if header.contains(';') { b';' } else { b',' };
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .trim(csv::Trim::All)
+            .from_reader(input.as_bytes());
+
+        let invalid = |err: csv::Error| DirectoryError::Csv {
+            line: err.position().map_or(0, |p| p.line()),
+            message: err.to_string(),
+        };
+        let headers = reader.headers().map_err(invalid)?.clone();
+        let mut directory = Directory::default();
+        for record in reader.records() {
+            let record = record.map_err(invalid)?;
+            let line = record.position().map_or(0, |p| p.line());
+            let subscriber: Subscriber = record.deserialize(Some(&headers)).map_err(invalid)?;
+            let address = subscriber.address;
+            if directory.subscribers.insert(address, subscriber).is_some() {
+                return Err(DirectoryError::DuplicateAddress { address, line });
+            }
+        }
+        directory.index_words();
+        Ok(directory)
+    }
+
+    fn index_words(&mut self) {
+        for (&address, subscriber) in &self.subscribers {
+            let mut words = tokens(&format!("{} {}", subscriber.name, subscriber.position));
+            words.sort();
+            words.dedup();
+            for word in &words {
+                self.word_index
+                    .entry(word.clone())
+                    .or_default()
+                    .push(address);
+            }
+            self.words.insert(address, words);
+        }
+    }
+
+    pub fn load_bytes(bytes: &[u8]) -> Result<Self, DirectoryError> {
+        Self::from_csv(&charset::decode_auto(bytes)?.text)
+    }
+
+    pub fn load_path(path: impl AsRef<std::path::Path>) -> Result<Self, DirectoryError> {
+        Self::load_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn get(&self, address: u32) -> Option<&Subscriber> {
+        self.subscribers.get(&address)
+    }
+
+    pub fn addresses(&self) -> Vec<u32> {
+        self.subscribers.keys().copied().collect()
+    }
+
+    pub fn subscribers(&self) -> impl Iterator<Item = &Subscriber> {
+        self.subscribers.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// label checks
+// -----------------------------------------------------------------------------
+
+/// Слова подписи или записи справочника в сравнимом виде: без регистра,
+/// знаков препинания и с `ё` -> `е` (`С-6 ПУ` -> `с6`, `пу`).
+fn tokens(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .map(|c| if c == 'ё' { 'е' } else { c })
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Подпись кнопки не соответствует абоненту, которого она вызывает.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelMismatch {
+    pub button: ButtonRef,
+    pub text: String,
+    pub subscriber: Subscriber,
+    /// Абонент, которому подпись соответствует лучше, если такой есть.
+    pub suggested: Option<Subscriber>,
+}
+
+impl std::fmt::Display for LabelMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:?} dials {}",
+            self.button, self.text, self.subscriber
+        )?;
+        match &self.suggested {
+            Some(other) => write!(f, ", but the label matches {}", other),
+            None => write!(f, ", which does not match the label"),
+        }
+    }
+}
+
+impl Directory {
+    /// Насколько подпись похожа на запись справочника. Слова, которые
+    /// встречаются у многих абонентов (`ПУ`, `диспетчер`), весят меньше
+    /// редких (`С-6`).
+    fn label_score(&self, label: &[String], address: u32) -> f32 {
+        let Some(own) = self.words.get(&address) else {
+            return 0.0;
+        };
+        label
+            .iter()
+            .filter(|word| own.binary_search(word).is_ok())
+            .map(|word| 1.0 / self.word_index.get(word).map_or(1, Vec::len) as f32)
+            .sum()
+    }
+
+    /// Проверяет подписи кнопок телефонных панелей по справочнику. Кнопка
+    /// попадает в отчёт, если её подпись больше похожа на другого абонента,
+    /// чем на вызываемого, или не имеет с ним ни одного общего слова.
+    /// Кнопки с адресами вне справочника и пустыми подписями пропускаются.
+    pub fn check_labels(&self, config: &ScreenConfig) -> Vec<LabelMismatch> {
+        let mut mismatches = Vec::new();
+        for panel in &config.phone_panels {
+            for button in &panel.buttons {
+                let label = tokens(&button.text);
+                let Some(subscriber) = self.get(button.internal_address) else {
+                    continue;
+                };
+                if label.is_empty() {
+                    continue;
+                }
+                let own_score = self.label_score(&label, subscriber.address);
+                // only subscribers sharing a word with the label can score
+                let mut candidates: Vec<u32> = label
+                    .iter()
+                    .filter_map(|word| self.word_index.get(word))
+                    .flatten()
+                    .copied()
+                    .collect();
+                candidates.sort();
+                candidates.dedup();
+                let best = candidates
+                    .into_iter()
+                    .filter_map(|address| self.subscribers.get(&address))
+                    .map(|s| (s, self.label_score(&label, s.address)))
+                    .filter(|&(_, score)| score > own_score)
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                if best.is_none() && own_score > 0.0 {
+                    continue;
+                }
+                mismatches.push(LabelMismatch {
+                    button: ButtonRef {
+                        kind: PanelKind::Phone,
+                        panel: panel.id.clone(),
+                        button: button.id.clone(),
+                    },
+                    text: button.text.clone(),
+                    subscriber: subscriber.clone(),
+                    suggested: best.map(|(s, _)| s.clone()),
+                });
+            }
+        }
+        mismatches
+    }
+}
+
+/// Правило `phone-directory-label`: сверка подписей кнопок со справочником.
+/// Подключается к [`crate::rules::Linter`], когда справочник загружен.
+pub struct DirectoryLabelRule {
+    pub directory: Directory,
+}
+
+impl Rule for DirectoryLabelRule {
+    fn id(&self) -> &str {
+        "phone-directory-label"
+    }
+
+    fn description(&self) -> &str {
+        "Phone button label does not match the subscriber directory"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, config: &ScreenConfig) -> Vec<Finding> {
+        self.directory
+            .check_labels(config)
+            .into_iter()
+            .map(|mismatch| Finding {
+                message: mismatch.to_string(),
+                buttons: vec![mismatch.button],
+            })
+            .collect()
+    }
+}
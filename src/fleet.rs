@@ -0,0 +1,574 @@
+use crate::charset::SubstitutionPolicy;
+use crate::config::{
+    self, EncodeError, EncodedConfig, LoadError, LoadedConfig, PhoneButton, ScreenConfig, load_path,
+};
+use crate::lint::{ButtonRef, PanelKind};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+// -----------------------------------------------------------------------------
+// fleet of workstations
+// -----------------------------------------------------------------------------
+
+/// Рабочее место: файл конфигурации и его содержимое.
+#[derive(Debug, Clone)]
+pub struct Workstation {
+    pub path: PathBuf,
+    pub loaded: LoadedConfig,
+}
+
+impl Workstation {
+    pub fn config(&self) -> &ScreenConfig {
+        &self.loaded.config
+    }
+
+    pub fn address(&self) -> Option<u32> {
+        self.loaded.config.internal_address
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.loaded.config.name.as_deref()
+    }
+}
+
+impl std::fmt::Display for Workstation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.address() {
+            Some(address) => write!(f, "{}", address)?,
+            None => write!(f, "-")?,
+        }
+        write!(f, " {}", self.name().unwrap_or("(unnamed)"))
+    }
+}
+
+/// Файл, который не удалось загрузить.
+#[derive(Debug)]
+pub struct FleetError {
+    pub path: PathBuf,
+    pub error: LoadError,
+}
+
+impl std::fmt::Display for FleetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+/// Все конфигурации площадки: каждый файл `*.conf` в дереве каталогов.
+///
+/// Рабочие места упорядочены по глобальному `internal_address`, затем по
+/// `name`; файлы без адреса идут в конце. Файлы, которые не удалось
+/// прочитать или разобрать, перечислены в `errors` и не мешают загрузке
+/// остальных.
+#[derive(Debug, Default)]
+pub struct Fleet {
+    pub root: PathBuf,
+    pub workstations: Vec<Workstation>,
+    pub errors: Vec<FleetError>,
+}
+
+impl Fleet {
+    pub fn load(root: impl AsRef<Path>) -> std::io::Result<Self> {
+        let root = root.as_ref();
+        let mut paths = Vec::new();
+        collect_configs(root, &mut paths)?;
+        paths.sort();
+
+        let mut fleet = Fleet {
+            root: root.to_path_buf(),
+            ..Default::default()
+        };
+        for path in paths {
+            match load_path(&path) {
+                Ok(loaded) => fleet.workstations.push(Workstation { path, loaded }),
+                Err(error) => fleet.errors.push(FleetError { path, error }),
+            }
+        }
+        fleet.workstations.sort_by_key(|ws| {
+            (
+                ws.address().is_none(),
+                ws.address(),
+                ws.name().map(str::to_owned),
+            )
+        });
+        Ok(fleet)
+    }
+
+    pub fn by_address(&self, address: u32) -> Option<&Workstation> {
+        self.workstations
+            .iter()
+            .find(|ws| ws.address() == Some(address))
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Workstation> {
+        self.workstations.iter().find(|ws| ws.name() == Some(name))
+    }
+
+    /// Путь к файлу относительно корня площадки, для вывода.
+    pub fn relative_path<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+}
+
+fn collect_configs(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_configs(&path, out)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("conf"))
+        {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// dangling addresses
+// -----------------------------------------------------------------------------
+
+/// Кнопка, адрес которой не принадлежит ни одному известному абоненту.
+#[derive(Debug, Clone)]
+pub struct DanglingButton<'a> {
+    pub workstation: &'a Workstation,
+    pub button: ButtonRef,
+    pub address: u32,
+    pub text: String,
+}
+
+impl std::fmt::Display for DanglingButton<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} {:?} dials unknown address {}",
+            self.workstation, self.button, self.text, self.address
+        )
+    }
+}
+
+/// Отчёт о "висящих" адресах площадки.
+#[derive(Debug, Clone, Default)]
+pub struct DanglingReport<'a> {
+    /// Кнопки, которые вызывают неизвестный адрес.
+    pub buttons: Vec<DanglingButton<'a>>,
+    /// Рабочие места, которые не может вызвать ни одна панель других
+    /// рабочих мест (в том числе места без глобального адреса).
+    pub uncallable: Vec<&'a Workstation>,
+}
+
+impl DanglingReport<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.buttons.is_empty() && self.uncallable.is_empty()
+    }
+}
+
+impl std::fmt::Display for DanglingReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for button in &self.buttons {
+            writeln!(f, "{}", button)?;
+        }
+        for ws in &self.uncallable {
+            writeln!(f, "{}: no other workstation can call it", ws)?;
+        }
+        Ok(())
+    }
+}
+
+impl Fleet {
+    /// Ищет кнопки, адрес которых не совпадает ни с одним рабочим местом
+    /// площадки и ни с одним адресом из `subscribers` (внешние абоненты,
+    /// импортированный справочник), а также рабочие места, до которых нельзя
+    /// дозвониться с других рабочих мест. Нулевые адреса пропускаются: о них
+    /// сообщает проверка `phone-missing-address`.
+    pub fn dangling_report(&self, subscribers: &[u32]) -> DanglingReport<'_> {
+        let known =
+            |address: u32| subscribers.contains(&address) || self.by_address(address).is_some();
+        let mut report = DanglingReport::default();
+
+        for ws in &self.workstations {
+            for panel in &ws.config().phone_panels {
+                for button in &panel.buttons {
+                    let address = button.internal_address;
+                    if address == 0 || known(address) {
+                        continue;
+                    }
+                    report.buttons.push(DanglingButton {
+                        workstation: ws,
+                        button: ButtonRef {
+                            kind: PanelKind::Phone,
+                            panel: panel.id.clone(),
+                            button: button.id.clone(),
+                        },
+                        address,
+                        text: button.text.clone(),
+                    });
+                }
+            }
+        }
+
+        for ws in &self.workstations {
+            let callable = ws.address().is_some_and(|address| {
+                self.workstations.iter().any(|other| {
+                    other.address() != Some(address)
+                        && other
+                            .config()
+                            .phone_panels
+                            .iter()
+                            .flat_map(|p| &p.buttons)
+                            .any(|b| b.internal_address == address)
+                })
+            });
+            if !callable {
+                report.uncallable.push(ws);
+            }
+        }
+        report
+    }
+}
+
+// -----------------------------------------------------------------------------
+// fleet-wide edits
+// -----------------------------------------------------------------------------
+
+/// Изменение одной кнопки телефонной панели.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ButtonEdit {
+    pub button: ButtonRef,
+    pub old_address: u32,
+    pub new_address: u32,
+    pub old_text: String,
+    pub new_text: String,
+}
+
+impl std::fmt::Display for ButtonEdit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} {:?} -> {} {:?}",
+            self.button, self.old_address, self.old_text, self.new_address, self.new_text
+        )
+    }
+}
+
+/// Изменённая строка файла (номера строк с единицы).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineChange {
+    pub line: usize,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Правка одного файла площадки: изменённые кнопки и новый текст файла.
+/// Текст получен из исходного через [`config::update_document`], поэтому
+/// всё, кроме изменённых значений, остаётся как было.
+#[derive(Debug, Clone)]
+pub struct FileEdit<'a> {
+    pub workstation: &'a Workstation,
+    pub buttons: Vec<ButtonEdit>,
+    pub text: String,
+}
+
+impl FileEdit<'_> {
+    /// Строки, которые отличаются от исходного текста.
+    pub fn changed_lines(&self) -> Vec<LineChange> {
+        let old: Vec<&str> = self.workstation.loaded.text.lines().collect();
+        let new: Vec<&str> = self.text.lines().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let old = &old[prefix..old.len() - suffix];
+        let new = &new[prefix..new.len() - suffix];
+        (0..old.len().max(new.len()))
+            .map(|i| LineChange {
+                line: prefix + i + 1,
+                old: old.get(i).map(|s| s.to_string()),
+                new: new.get(i).map(|s| s.to_string()),
+            })
+            .filter(|change| change.old != change.new)
+            .collect()
+    }
+
+    /// Байты нового файла в исходной кодировке.
+    pub fn encode(&self) -> Result<EncodedConfig, EditError> {
+        config::encode_config(
+            &self.text,
+            self.workstation.loaded.encoding,
+            SubstitutionPolicy::Strict,
+        )
+        .map_err(|error| EditError::Encode {
+            path: self.workstation.path.clone(),
+            error,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), EditError> {
+        let encoded = self.encode()?;
+        std::fs::write(&self.workstation.path, encoded.bytes).map_err(|error| EditError::Io {
+            path: self.workstation.path.clone(),
+            error,
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum EditError {
+    #[error("{}: {error}", path.display())]
+    Encode { path: PathBuf, error: EncodeError },
+    #[error("{}: {error}", path.display())]
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+}
+
+/// Правка площадки: только файлы, в которых что-то изменилось. До вызова
+/// [`FleetEdit::save`] ничего не записывается, так что её `Display` служит
+/// предварительным просмотром.
+#[derive(Debug, Clone)]
+pub struct FleetEdit<'a> {
+    pub fleet: &'a Fleet,
+    pub files: Vec<FileEdit<'a>>,
+}
+
+impl FleetEdit<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn button_count(&self) -> usize {
+        self.files.iter().map(|file| file.buttons.len()).sum()
+    }
+
+    /// Проверяет, что все файлы можно записать в их кодировке, и только
+    /// затем сохраняет их.
+    pub fn save(&self) -> Result<(), EditError> {
+        for file in &self.files {
+            file.encode()?;
+        }
+        self.files.iter().try_for_each(FileEdit::save)
+    }
+}
+
+impl std::fmt::Display for FleetEdit<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for file in &self.files {
+            writeln!(
+                f,
+                "{} ({})",
+                self.fleet.relative_path(&file.workstation.path).display(),
+                file.workstation
+            )?;
+            for button in &file.buttons {
+                writeln!(f, "  {}", button)?;
+            }
+            for change in file.changed_lines() {
+                if let Some(old) = &change.old {
+                    writeln!(f, "  {:>5} - {}", change.line, old)?;
+                }
+                if let Some(new) = &change.new {
+                    writeln!(f, "  {:>5} + {}", change.line, new)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Fleet {
+    /// Применяет `edit` к каждой кнопке телефонных панелей площадки и
+    /// собирает файлы, в которых изменился адрес или подпись хотя бы одной
+    /// кнопки.
+    pub fn edit_phone_buttons(
+        &self,
+        mut edit: impl FnMut(&Workstation, &mut PhoneButton),
+    ) -> FleetEdit<'_> {
+        let mut result = FleetEdit {
+            fleet: self,
+            files: Vec::new(),
+        };
+        for ws in &self.workstations {
+            let mut config = ws.config().clone();
+            let mut buttons = Vec::new();
+            for panel in &mut config.phone_panels {
+                for button in &mut panel.buttons {
+                    let (old_address, old_text) = (button.internal_address, button.text.clone());
+                    edit(ws, button);
+                    if button.internal_address == old_address && button.text == old_text {
+                        continue;
+                    }
+                    buttons.push(ButtonEdit {
+                        button: ButtonRef {
+                            kind: PanelKind::Phone,
+                            panel: panel.id.clone(),
+                            button: button.id.clone(),
+                        },
+                        old_address,
+                        new_address: button.internal_address,
+                        old_text,
+                        new_text: button.text.clone(),
+                    });
+                }
+            }
+            if buttons.is_empty() {
+                continue;
+            }
+            let mut doc = config::parse_document(&ws.loaded.text);
+            config::update_document(&mut doc, &config);
+            result.files.push(FileEdit {
+                workstation: ws,
+                buttons,
+                text: doc.to_string(),
+            });
+        }
+        result
+    }
+
+    /// Переводит все кнопки с адреса `from` на адрес `to` (например, при
+    /// переезде абонента) и, если задано, меняет их подпись.
+    pub fn replace_address(&self, from: u32, to: u32, label: Option<&str>) -> FleetEdit<'_> {
+        self.edit_phone_buttons(|_, button| {
+            if button.internal_address == from {
+                button.internal_address = to;
+                if let Some(label) = label {
+                    button.text = label.to_string();
+                }
+            }
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// label consistency
+// -----------------------------------------------------------------------------
+
+/// Одна из подписей адреса и рабочие места, на которых она используется.
+#[derive(Debug, Clone)]
+pub struct LabelVariant<'a> {
+    pub text: String,
+    pub workstations: Vec<&'a Workstation>,
+    /// Сколько всего кнопок с этой подписью (на одном рабочем месте их может
+    /// быть несколько).
+    pub buttons: usize,
+}
+
+/// Все подписи кнопок, вызывающих один адрес. Варианты упорядочены по
+/// числу кнопок, самый распространённый - первый.
+#[derive(Debug, Clone)]
+pub struct AddressLabels<'a> {
+    pub address: u32,
+    pub variants: Vec<LabelVariant<'a>>,
+}
+
+impl AddressLabels<'_> {
+    pub fn is_consistent(&self) -> bool {
+        self.variants.len() <= 1
+    }
+}
+
+impl std::fmt::Display for AddressLabels<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:", self.address)?;
+        for (idx, variant) in self.variants.iter().enumerate() {
+            let workstations: Vec<String> = variant
+                .workstations
+                .iter()
+                .map(|ws| ws.to_string())
+                .collect();
+            writeln!(
+                f,
+                "  [{}] {:?} - {}",
+                idx + 1,
+                variant.text,
+                workstations.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Подписи кнопок площадки, сгруппированные по адресу.
+#[derive(Debug, Clone, Default)]
+pub struct LabelReport<'a> {
+    pub addresses: Vec<AddressLabels<'a>>,
+}
+
+impl<'a> LabelReport<'a> {
+    pub fn get(&self, address: u32) -> Option<&AddressLabels<'a>> {
+        self.addresses.iter().find(|a| a.address == address)
+    }
+
+    /// Адреса, которые подписаны по-разному.
+    pub fn inconsistent(&self) -> impl Iterator<Item = &AddressLabels<'a>> {
+        self.addresses.iter().filter(|a| !a.is_consistent())
+    }
+}
+
+impl std::fmt::Display for LabelReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for address in self.inconsistent() {
+            write!(f, "{}", address)?;
+        }
+        Ok(())
+    }
+}
+
+impl Fleet {
+    /// Собирает подписи всех кнопок телефонных панелей по адресам. Нулевые
+    /// адреса пропускаются.
+    pub fn label_report(&self) -> LabelReport<'_> {
+        let mut by_address: BTreeMap<u32, Vec<LabelVariant<'_>>> = BTreeMap::new();
+        for ws in &self.workstations {
+            for button in ws.config().phone_panels.iter().flat_map(|p| &p.buttons) {
+                if button.internal_address == 0 {
+                    continue;
+                }
+                let variants = by_address.entry(button.internal_address).or_default();
+                let idx = match variants.iter().position(|v| v.text == button.text) {
+                    Some(idx) => idx,
+                    None => {
+                        variants.push(LabelVariant {
+                            text: button.text.clone(),
+                            workstations: Vec::new(),
+                            buttons: 0,
+                        });
+                        variants.len() - 1
+                    }
+                };
+                let variant = &mut variants[idx];
+                variant.buttons += 1;
+                if !variant
+                    .workstations
+                    .iter()
+                    .any(|&other| std::ptr::eq(other, ws))
+                {
+                    variant.workstations.push(ws);
+                }
+            }
+        }
+        let addresses = by_address
+            .into_iter()
+            .map(|(address, mut variants)| {
+                // stable sort keeps the fleet order for equally used labels
+                variants.sort_by_key(|v| std::cmp::Reverse(v.buttons));
+                AddressLabels { address, variants }
+            })
+            .collect();
+        LabelReport { addresses }
+    }
+
+    /// Подписывает все кнопки, вызывающие `address`, одинаково.
+    pub fn apply_label(&self, address: u32, label: &str) -> FleetEdit<'_> {
+        self.edit_phone_buttons(|_, button| {
+            if button.internal_address == address {
+                button.text = label.to_string();
+            }
+        })
+    }
+}
@@ -0,0 +1,117 @@
+use crate::config::{self, ScreenConfig, delocalize_panel_label, localize_panel_label};
+use thiserror::Error;
+
+// -----------------------------------------------------------------------------
+// structured formats
+// -----------------------------------------------------------------------------
+
+/// Формат структурированной копии конфигурации, удобной для просмотра,
+/// сравнения и хранения в системе контроля версий.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructuredFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl StructuredFormat {
+    pub const ALL: [StructuredFormat; 3] = [Self::Json, Self::Yaml, Self::Toml];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+        }
+    }
+
+    /// Определяет формат по расширению файла (`json`, `yaml`/`yml`, `toml`).
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?;
+        Self::from_extension(ext)
+    }
+}
+
+impl std::fmt::Display for StructuredFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json => write!(f, "JSON"),
+            Self::Yaml => write!(f, "YAML"),
+            Self::Toml => write!(f, "TOML"),
+        }
+    }
+}
+
+impl std::str::FromStr for StructuredFormat {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_extension(s).ok_or_else(|| FormatError::UnknownFormat(s.to_string()))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// error types
+// -----------------------------------------------------------------------------
+
+#[derive(Error, Debug)]
+pub enum FormatError {
+    #[error("Unknown structured format: {0}")]
+    UnknownFormat(String),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_norway::Error),
+    #[error("TOML error: {0}")]
+    TomlWrite(#[from] toml::ser::Error),
+    #[error("TOML error: {0}")]
+    TomlRead(#[from] toml::de::Error),
+}
+
+// -----------------------------------------------------------------------------
+// export and import
+// -----------------------------------------------------------------------------
+
+/// Записывает конфигурацию в структурированном формате.
+///
+/// Телефонные панели выгружаются под именами секций (`Panel01`), а не под
+/// подписями интерфейса (`ПД 1`): копия описывает файл, а не экран.
+pub fn export(config: &ScreenConfig, format: StructuredFormat) -> Result<String, FormatError> {
+    let mut config = config.clone();
+    for panel in &mut config.phone_panels {
+        panel.id = delocalize_panel_label(&panel.id);
+    }
+    Ok(match format {
+        StructuredFormat::Json => serde_json::to_string_pretty(&config)? + "\n",
+        StructuredFormat::Yaml => serde_norway::to_string(&config)?,
+        StructuredFormat::Toml => toml::to_string_pretty(&config)?,
+    })
+}
+
+/// Читает конфигурацию, ранее выгруженную через [`export`]. Панели,
+/// записанные подписями интерфейса (`ПД 1`), тоже принимаются.
+pub fn import(input: &str, format: StructuredFormat) -> Result<ScreenConfig, FormatError> {
+    let mut config: ScreenConfig = match format {
+        StructuredFormat::Json => serde_json::from_str(input)?,
+        StructuredFormat::Yaml => serde_norway::from_str(input)?,
+        StructuredFormat::Toml => toml::from_str(input)?,
+    };
+    for panel in &mut config.phone_panels {
+        panel.id = localize_panel_label(&panel.id);
+    }
+    Ok(config)
+}
+
+/// Преобразует структурированную копию обратно в текст `.conf`.
+pub fn import_to_conf(input: &str, format: StructuredFormat) -> Result<String, FormatError> {
+    import(input, format).map(|config| config::serialize(&config))
+}
@@ -1,29 +1,99 @@
-use std::iter::Peekable;
+use std::iter::{Enumerate, Peekable};
 use std::str::Lines;
 
+/// Положение строки в исходном файле вместе с путём секций до неё,
+/// например `PhonePanels/Panel03/Button12/position_x`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub raw: String,
+    pub path: String,
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {} ({}): `{}`",
+            self.line, self.column, self.path, self.raw
+        )
+    }
+}
+
+fn make_location<S: AsRef<str>>(
+    line: usize,
+    raw: &str,
+    sections: &[S],
+    key: &str,
+    fragment: &str,
+) -> Location {
+    let start = raw.as_ptr() as usize;
+    let offset = (fragment.as_ptr() as usize)
+        .checked_sub(start)
+        .filter(|offset| *offset <= raw.len())
+        .unwrap_or(raw.len() - raw.trim_start().len());
+    let mut path = sections
+        .iter()
+        .map(AsRef::as_ref)
+        .collect::<Vec<_>>()
+        .join("/");
+    if !key.is_empty() {
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(key);
+    }
+    Location {
+        line,
+        column: raw[..offset].chars().count() + 1,
+        raw: raw.to_string(),
+        path,
+    }
+}
+
 pub struct LineScanner<'a> {
-    iter: Peekable<Lines<'a>>,
+    iter: Peekable<Enumerate<Lines<'a>>>,
+    line: usize,
+    raw: &'a str,
+    open_sections: Vec<&'a str>,
 }
 
 impl<'a> LineScanner<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
-            iter: input.lines().peekable(),
+            iter: input.lines().enumerate().peekable(),
+            line: 0,
+            raw: "",
+            open_sections: Vec::new(),
         }
     }
 
     pub fn next_line(&mut self) -> Option<&'a str> {
-        while let Some(line) = self.iter.next() {
+        for (idx, line) in self.iter.by_ref() {
             let trimmed = line.trim();
             if !trimmed.is_empty() {
+                self.line = idx + 1;
+                self.raw = line;
+                self.track_section(trimmed);
                 return Some(trimmed);
             }
         }
         None
     }
 
+    /// Следующая строка целиком, включая пустые и пробельные вокруг
+    /// содержимого. Секции отслеживаются так же, как в `next_line`.
+    pub fn next_raw_line(&mut self) -> Option<&'a str> {
+        let (idx, line) = self.iter.next()?;
+        self.line = idx + 1;
+        self.raw = line;
+        self.track_section(line.trim());
+        Some(line)
+    }
+
     pub fn peek_line(&mut self) -> Option<&'a str> {
-        while let Some(line) = self.iter.peek() {
+        while let Some((_, line)) = self.iter.peek() {
             let trimmed = line.trim();
             if trimmed.is_empty() {
                 self.iter.next();
@@ -34,6 +104,23 @@ impl<'a> LineScanner<'a> {
         None
     }
 
+    /// Положение фрагмента `fragment` последней прочитанной строки. Фрагмент
+    /// должен быть срезом этой строки (как возвращают `next_line` и `parse_kv`),
+    /// иначе позицией считается начало строки.
+    pub fn location(&self, key: &str, fragment: &str) -> Location {
+        make_location(self.line, self.raw, &self.open_sections, key, fragment)
+    }
+
+    fn track_section(&mut self, line: &'a str) {
+        if let Some(name) = Self::get_section_name(line) {
+            self.open_sections.push(name);
+        } else if let Some(name) = Self::get_closing_name(line)
+            && let Some(depth) = self.open_sections.iter().rposition(|s| *s == name)
+        {
+            self.open_sections.truncate(depth);
+        }
+    }
+
     pub fn parse_kv(line: &str) -> Option<(&str, &str)> {
         let parts: Vec<&str> = line.splitn(2, '=').collect();
         if parts.len() == 2 {
@@ -43,14 +130,22 @@ impl<'a> LineScanner<'a> {
         }
     }
 
+    /// Значение строкового поля без обрамляющих кавычек. Кавычки внутри
+    /// значения не экранируются: значением считается всё между первой и
+    /// последней кавычкой, поэтому `"КСРС "инж""` читается как `КСРС "инж"`.
     pub fn clean_string(val: &str) -> String {
-        val.trim_matches('"').to_string()
+        let val = val.strip_prefix('"').unwrap_or(val);
+        val.strip_suffix('"').unwrap_or(val).to_string()
     }
 
     pub fn is_closing_tag(line: &str, section_name: &str) -> bool {
         line == format!("[#{}]", section_name)
     }
 
+    pub fn get_closing_name(line: &str) -> Option<&str> {
+        line.strip_prefix("[#")?.strip_suffix(']')
+    }
+
     pub fn get_section_name(line: &str) -> Option<&str> {
         if line.starts_with('[') && !line.starts_with("[#") && line.ends_with(']') {
             Some(line.trim_matches(|c| c == '[' || c == ']'))
@@ -59,3 +154,486 @@ impl<'a> LineScanner<'a> {
         }
     }
 }
+
+pub struct LineWriter {
+    out: String,
+}
+
+impl Default for LineWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineWriter {
+    pub fn new() -> Self {
+        Self { out: String::new() }
+    }
+
+    pub fn open_section(&mut self, section_name: &str) {
+        self.line(&format!("[{}]", section_name));
+    }
+
+    pub fn close_section(&mut self, section_name: &str) {
+        self.line(&format!("[#{}]", section_name));
+    }
+
+    pub fn write_kv(&mut self, key: &str, value: impl std::fmt::Display) {
+        self.line(&format!("{} = {}", key, value));
+    }
+
+    /// Обрамляет значение кавычками; парная операция к
+    /// [`LineScanner::clean_string`].
+    pub fn quote_string(val: &str) -> String {
+        format!("\"{}\"", val)
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+
+    fn line(&mut self, line: &str) {
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+}
+
+// -----------------------------------------------------------------------------
+// lossless document tree
+// -----------------------------------------------------------------------------
+
+/// Дерево документа в скобочном формате, сохраняющее исходный текст без потерь.
+///
+/// Каждая строка файла попадает в дерево как есть: неизвестные ключи и секции,
+/// пустые строки, отступы и порядок следования сохраняются, поэтому
+/// `Document::parse(text).to_string() == text`. Глубина вложенности секций не
+/// ограничена, каждый узел помнит своё положение в файле.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    pub root: Section,
+    pub issues: Vec<StructureIssue>,
+    line_ending: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Entry(Entry),
+    Section(Section),
+    Raw(RawLine),
+}
+
+/// Секция `[name]` ... `[#name]`. У корневой секции документа нет тегов.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub children: Vec<Node>,
+    pub location: Location,
+    open: Option<String>,
+    close: Option<String>,
+}
+
+/// Строка вида `key = value`. Значение хранится в исходном виде, включая кавычки.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub location: Location,
+    value: String,
+    prefix: String,
+    suffix: String,
+}
+
+/// Строка, которая не является ни тегом секции, ни парой `key = value`:
+/// пустая строка, мусор или закрывающий тег без открытой секции.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawLine {
+    pub text: String,
+    pub location: Location,
+}
+
+/// Нарушение вложенности тегов, найденное при построении дерева.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StructureIssue {
+    /// Секция не закрыта: файл закончился или началась соседняя секция.
+    Unclosed { name: String, location: Location },
+    /// Закрывающий тег внешней секции встретился раньше тега вложенной.
+    Mismatched {
+        expected: String,
+        found: String,
+        line: usize,
+        location: Location,
+    },
+    /// Закрывающий тег, которому не соответствует ни одна открытая секция.
+    StrayClosing { name: String, location: Location },
+}
+
+/// Как достраивать дерево, если в файле пропущены закрывающие теги.
+///
+/// По умолчанию теги сопоставляются строго: секция закрывается только своим
+/// `[#name]`, поэтому `[Group1]` может содержать `[Group2]` на любой глубине.
+/// Форматы, в которых однородные секции не вкладываются друг в друга,
+/// включают `close_siblings`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TreeOptions<'a> {
+    /// Открывающий тег секции того же вида, что одна из открытых
+    /// (`[Button04]` внутри незакрытой `[Button03]`), закрывает её.
+    pub close_siblings: bool,
+    /// Имена секций, которые при `close_siblings` тоже считаются
+    /// однородными, например секции верхнего уровня конкретного формата.
+    pub sibling_groups: &'a [&'a [&'a str]],
+}
+
+impl Document {
+    pub fn parse(input: &str) -> Self {
+        Self::parse_with_options(input, TreeOptions::default())
+    }
+
+    pub fn parse_with_options(input: &str, options: TreeOptions) -> Self {
+        let line_ending = match input.find('\n') {
+            Some(pos) if input[..pos].ends_with('\r') => "\r\n",
+            _ => "\n",
+        };
+        let mut builder = TreeBuilder {
+            stack: vec![Section::default()],
+            issues: Vec::new(),
+        };
+
+        for (idx, raw) in input.split_inclusive('\n').enumerate() {
+            let number = idx + 1;
+            let line = raw.trim();
+            let text = raw.trim_end_matches(['\r', '\n']);
+
+            if let Some(name) = LineScanner::get_section_name(line) {
+                let is_sibling = |open: &Section| {
+                    section_kind(&open.name) == section_kind(name)
+                        || options
+                            .sibling_groups
+                            .iter()
+                            .any(|g| g.contains(&open.name.as_str()) && g.contains(&name))
+                };
+                if options.close_siblings
+                    && let Some(depth) = builder.stack.iter().skip(1).rposition(is_sibling)
+                {
+                    builder.close_unclosed(depth + 1);
+                }
+                let mut location = builder.location(number, text, "", name);
+                location.path = match location.path.is_empty() {
+                    true => name.to_string(),
+                    false => format!("{}/{}", location.path, name),
+                };
+                builder.stack.push(Section {
+                    name: name.to_string(),
+                    location,
+                    open: Some(raw.to_string()),
+                    ..Default::default()
+                });
+            } else if let Some(name) = LineScanner::get_closing_name(line) {
+                match builder.stack.iter().skip(1).rposition(|s| s.name == name) {
+                    Some(depth) => {
+                        while builder.stack.len() > depth + 2 {
+                            if let Some(section) = builder.stack.last() {
+                                builder.issues.push(StructureIssue::Mismatched {
+                                    expected: section.name.clone(),
+                                    found: name.to_string(),
+                                    line: number,
+                                    location: section.location.clone(),
+                                });
+                            }
+                            builder.close_innermost();
+                        }
+                        if let Some(section) = builder.stack.last_mut() {
+                            section.close = Some(raw.to_string());
+                        }
+                        builder.close_innermost();
+                    }
+                    None => {
+                        let location = builder.location(number, text, "", line);
+                        builder.issues.push(StructureIssue::StrayClosing {
+                            name: name.to_string(),
+                            location: location.clone(),
+                        });
+                        builder.push(Node::Raw(RawLine {
+                            text: raw.to_string(),
+                            location,
+                        }));
+                    }
+                }
+            } else if let Some((key, _)) = LineScanner::parse_kv(line) {
+                let location = builder.location(number, text, key, key);
+                builder.push(Node::Entry(Entry::from_raw(raw, key, location)));
+            } else {
+                let location = builder.location(number, text, "", line);
+                builder.push(Node::Raw(RawLine {
+                    text: raw.to_string(),
+                    location,
+                }));
+            }
+        }
+        builder.close_unclosed(1);
+
+        Self {
+            root: builder.stack.pop().unwrap_or_default(),
+            issues: builder.issues,
+            line_ending: line_ending.to_string(),
+        }
+    }
+
+    pub fn line_ending(&self) -> &str {
+        &self.line_ending
+    }
+}
+
+impl std::fmt::Display for Document {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.root.fmt(f)
+    }
+}
+
+/// Вид секции без порядкового номера: `Button03` -> `Button`.
+fn section_kind(section_name: &str) -> &str {
+    section_name.trim_end_matches(|c: char| c.is_ascii_digit())
+}
+
+struct TreeBuilder {
+    stack: Vec<Section>,
+    issues: Vec<StructureIssue>,
+}
+
+impl TreeBuilder {
+    fn location(&self, line: usize, raw: &str, key: &str, fragment: &str) -> Location {
+        let sections: Vec<&str> = self.stack.iter().skip(1).map(|s| s.name.as_str()).collect();
+        make_location(line, raw, &sections, key, fragment)
+    }
+
+    fn push(&mut self, node: Node) {
+        if let Some(section) = self.stack.last_mut() {
+            section.children.push(node);
+        }
+    }
+
+    fn close_innermost(&mut self) {
+        if let Some(section) = self.stack.pop() {
+            self.push(Node::Section(section));
+        }
+    }
+
+    /// Закрывает без тегов все секции глубже `depth`, отмечая их незакрытыми.
+    fn close_unclosed(&mut self, depth: usize) {
+        while self.stack.len() > depth {
+            if let Some(section) = self.stack.last() {
+                self.issues.push(StructureIssue::Unclosed {
+                    name: section.name.clone(),
+                    location: section.location.clone(),
+                });
+            }
+            self.close_innermost();
+        }
+    }
+}
+
+impl Section {
+    /// Создаёт новую секцию с открывающим и закрывающим тегами.
+    pub fn new(name: &str, line_ending: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            children: Vec::new(),
+            location: Location::default(),
+            open: Some(format!("[{}]{}", name, line_ending)),
+            close: Some(format!("[#{}]{}", name, line_ending)),
+        }
+    }
+
+    pub fn entry(&self, key: &str) -> Option<&Entry> {
+        self.children.iter().find_map(|node| match node {
+            Node::Entry(entry) if entry.key == key => Some(entry),
+            _ => None,
+        })
+    }
+
+    pub fn entry_mut(&mut self, key: &str) -> Option<&mut Entry> {
+        self.children.iter_mut().find_map(|node| match node {
+            Node::Entry(entry) if entry.key == key => Some(entry),
+            _ => None,
+        })
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections().find(|section| section.name == name)
+    }
+
+    pub fn section_mut(&mut self, name: &str) -> Option<&mut Section> {
+        self.children.iter_mut().find_map(|node| match node {
+            Node::Section(section) if section.name == name => Some(section),
+            _ => None,
+        })
+    }
+
+    /// Ищет вложенную секцию по пути вида `PhonePanels/Panel01/Button02`.
+    pub fn find(&self, path: &str) -> Option<&Section> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |section, name| section.section(name))
+    }
+
+    pub fn sections(&self) -> impl Iterator<Item = &Section> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Section(section) => Some(section),
+            _ => None,
+        })
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Entry(entry) => Some(entry),
+            _ => None,
+        })
+    }
+
+    /// Вставляет запись, сохраняя алфавитный порядок ключей, в котором пишет пульт.
+    pub fn insert_entry(&mut self, entry: Entry) {
+        let last_entry = self
+            .children
+            .iter()
+            .rposition(|node| matches!(node, Node::Entry(_)));
+        let position = self
+            .children
+            .iter()
+            .position(|node| matches!(node, Node::Entry(e) if e.key > entry.key))
+            .or(last_entry.map(|idx| idx + 1))
+            .unwrap_or(0);
+        let line_ending = line_ending_of(&entry.suffix).to_string();
+        self.insert_node(position, Node::Entry(entry), &line_ending);
+    }
+
+    /// Вставляет вложенную секцию на место `position` среди дочерних узлов.
+    pub fn insert_section(&mut self, position: usize, section: Section) {
+        let open = section.open.as_deref().unwrap_or_default();
+        let line_ending = line_ending_of(open).to_string();
+        self.insert_node(position, Node::Section(section), &line_ending);
+    }
+
+    /// Вставляет узел так, чтобы он не слился с соседними строками: у
+    /// последней строки файла может не быть перевода строки, и тогда новый
+    /// узел после неё или перенесённый с конца узел перед другими оказались
+    /// бы на одной строке с соседом.
+    fn insert_node(&mut self, position: usize, mut node: Node, line_ending: &str) {
+        let previous = match position.checked_sub(1) {
+            Some(idx) => self.children.get_mut(idx).and_then(last_line_mut),
+            None => self.open.as_mut(),
+        };
+        end_line(previous, line_ending);
+        if position < self.children.len() {
+            end_line(last_line_mut(&mut node), line_ending);
+        }
+        self.children.insert(position, node);
+    }
+
+    pub fn remove_entry(&mut self, key: &str) -> Option<Entry> {
+        let position = self
+            .children
+            .iter()
+            .position(|node| matches!(node, Node::Entry(e) if e.key == key))?;
+        match self.children.remove(position) {
+            Node::Entry(entry) => Some(entry),
+            _ => None,
+        }
+    }
+}
+
+/// Текст последней строки узла (для секции - закрывающий тег или последняя
+/// строка содержимого).
+fn last_line_mut(node: &mut Node) -> Option<&mut String> {
+    match node {
+        Node::Entry(entry) => Some(&mut entry.suffix),
+        Node::Raw(raw) => Some(&mut raw.text),
+        Node::Section(section) => {
+            if section.close.is_some() {
+                return section.close.as_mut();
+            }
+            match section.children.last_mut() {
+                Some(child) => last_line_mut(child),
+                None => section.open.as_mut(),
+            }
+        }
+    }
+}
+
+fn end_line(line: Option<&mut String>, line_ending: &str) {
+    if let Some(line) = line
+        && !line.ends_with('\n')
+    {
+        line.push_str(line_ending);
+    }
+}
+
+fn line_ending_of(line: &str) -> &str {
+    if line.ends_with("\r\n") { "\r\n" } else { "\n" }
+}
+
+impl std::fmt::Display for Section {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(open) = &self.open {
+            f.write_str(open)?;
+        }
+        for node in &self.children {
+            match node {
+                Node::Entry(entry) => entry.fmt(f)?,
+                Node::Section(section) => section.fmt(f)?,
+                Node::Raw(raw) => f.write_str(&raw.text)?,
+            }
+        }
+        if let Some(close) = &self.close {
+            f.write_str(close)?;
+        }
+        Ok(())
+    }
+}
+
+impl Entry {
+    pub fn new(key: &str, value: &str, line_ending: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            location: Location::default(),
+            value: value.to_string(),
+            prefix: format!("{} = ", key),
+            suffix: line_ending.to_string(),
+        }
+    }
+
+    fn from_raw(raw: &str, key: &str, location: Location) -> Self {
+        let body = raw.trim_end();
+        let eq = body.find('=').unwrap_or(body.len());
+        let after = &body[(eq + 1).min(body.len())..];
+        let value_start = body.len() - after.trim_start().len();
+        Self {
+            key: key.to_string(),
+            location,
+            value: body[value_start..].to_string(),
+            prefix: raw[..value_start].to_string(),
+            suffix: raw[body.len()..].to_string(),
+        }
+    }
+
+    /// Положение значения: как [`Entry::location`], но со столбцом начала значения.
+    pub fn value_location(&self) -> Location {
+        Location {
+            column: self.prefix.chars().count() + 1,
+            ..self.location.clone()
+        }
+    }
+
+    /// Исходный текст значения (строки остаются в кавычках).
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Заменяет значение, не трогая ключ, отступы и окончание строки.
+    pub fn set_value(&mut self, value: &str) {
+        self.value = value.to_string();
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.prefix, self.value, self.suffix)
+    }
+}
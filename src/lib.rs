@@ -1,5 +1,15 @@
+pub mod bracket;
+pub mod callgraph;
+pub mod charset;
 pub mod config;
+pub mod diff;
+pub mod directory;
+pub mod fleet;
+pub mod formats;
 pub mod generic;
+pub mod lint;
+pub mod merge;
+pub mod rules;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,407 @@
+use crate::config::{PhoneButton, RadioButton, ScreenConfig, Severity};
+use serde::Deserialize;
+
+// -----------------------------------------------------------------------------
+// layout issues
+// -----------------------------------------------------------------------------
+
+// Overlaps smaller than this (in squared screen fractions) are rounding noise
+// of buttons that merely touch each other.
+const AREA_EPSILON: f32 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PanelKind {
+    Phone,
+    Radio,
+}
+
+impl std::fmt::Display for PanelKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PanelKind::Phone => f.write_str("phone panel"),
+            PanelKind::Radio => f.write_str("radio panel"),
+        }
+    }
+}
+
+/// Кнопка, к которой относится замечание: вид и идентификатор панели и
+/// идентификатор кнопки (`ПД 1` / `Button03`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ButtonRef {
+    pub kind: PanelKind,
+    pub panel: String,
+    pub button: String,
+}
+
+impl std::fmt::Display for ButtonRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.kind, self.panel, self.button)
+    }
+}
+
+/// Прямоугольник кнопки в нормализованных координатах экрана (0..1).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn intersection_area(&self, other: &Rect) -> f32 {
+        let w = (self.x + self.width).min(other.x + other.width) - self.x.max(other.x);
+        let h = (self.y + self.height).min(other.y + other.height) - self.y.max(other.y);
+        if w > 0.0 && h > 0.0 { w * h } else { 0.0 }
+    }
+
+    fn within_screen(&self) -> bool {
+        self.x >= 0.0 && self.y >= 0.0 && self.x + self.width <= 1.0 && self.y + self.height <= 1.0
+    }
+}
+
+impl From<&PhoneButton> for Rect {
+    fn from(b: &PhoneButton) -> Self {
+        Rect {
+            x: b.position_x,
+            y: b.position_y,
+            width: b.size_width,
+            height: b.size_height,
+        }
+    }
+}
+
+impl From<&RadioButton> for Rect {
+    fn from(b: &RadioButton) -> Self {
+        Rect {
+            x: b.position_x,
+            y: b.position_y,
+            width: b.size_width,
+            height: b.size_height,
+        }
+    }
+}
+
+/// Ошибка раскладки кнопок на панели.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutIssue {
+    /// Две кнопки одной панели перекрываются; `area` - площадь перекрытия в
+    /// долях экрана.
+    Overlap {
+        first: ButtonRef,
+        second: ButtonRef,
+        area: f32,
+    },
+    /// Ширина или высота кнопки отрицательна.
+    NegativeSize { button: ButtonRef, rect: Rect },
+    /// Кнопка выходит за границы экрана 0..1.
+    OutOfBounds { button: ButtonRef, rect: Rect },
+}
+
+impl std::fmt::Display for LayoutIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayoutIssue::Overlap {
+                first,
+                second,
+                area,
+            } => write!(
+                f,
+                "{} overlaps {} by {:.4} of the screen",
+                first, second.button, area
+            ),
+            LayoutIssue::NegativeSize { button, rect } => write!(
+                f,
+                "{} has negative size {}x{}",
+                button, rect.width, rect.height
+            ),
+            LayoutIssue::OutOfBounds { button, rect } => write!(
+                f,
+                "{} lies outside the screen: x={} y={} w={} h={}",
+                button, rect.x, rect.y, rect.width, rect.height
+            ),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// layout checks
+// -----------------------------------------------------------------------------
+
+/// Проверяет раскладку всех панелей: перекрытия кнопок внутри одной панели,
+/// отрицательные размеры и выход за границы экрана.
+pub fn check_layout(config: &ScreenConfig) -> Vec<LayoutIssue> {
+    let mut issues = Vec::new();
+    for panel in &config.phone_panels {
+        let buttons: Vec<_> = panel
+            .buttons
+            .iter()
+            .map(|b| (b.id.as_str(), Rect::from(b)))
+            .collect();
+        check_panel(PanelKind::Phone, &panel.id, &buttons, &mut issues);
+    }
+    for panel in &config.radio_panels {
+        let buttons: Vec<_> = panel
+            .buttons
+            .iter()
+            .map(|b| (b.id.as_str(), Rect::from(b)))
+            .collect();
+        check_panel(PanelKind::Radio, &panel.id, &buttons, &mut issues);
+    }
+    issues
+}
+
+fn check_panel(
+    kind: PanelKind,
+    panel: &str,
+    buttons: &[(&str, Rect)],
+    issues: &mut Vec<LayoutIssue>,
+) {
+    let button_ref = |id: &str| ButtonRef {
+        kind,
+        panel: panel.to_string(),
+        button: id.to_string(),
+    };
+
+    for &(id, rect) in buttons {
+        if rect.width < 0.0 || rect.height < 0.0 {
+            issues.push(LayoutIssue::NegativeSize {
+                button: button_ref(id),
+                rect,
+            });
+        } else if !rect.within_screen() {
+            issues.push(LayoutIssue::OutOfBounds {
+                button: button_ref(id),
+                rect,
+            });
+        }
+    }
+
+    for (i, &(first, a)) in buttons.iter().enumerate() {
+        for &(second, b) in &buttons[i + 1..] {
+            let area = a.intersection_area(&b);
+            if area > AREA_EPSILON {
+                issues.push(LayoutIssue::Overlap {
+                    first: button_ref(first),
+                    second: button_ref(second),
+                    area,
+                });
+            }
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// radio slots
+// -----------------------------------------------------------------------------
+
+// Slot value of a station that is not bound to any radio button.
+const UNASSIGNED_SLOT: i32 = -1;
+
+/// Несоответствие между слотами кнопок радиопанелей и доступными
+/// радиостанциями.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RadioSlotIssue {
+    /// Слоту кнопки не соответствует ни одна доступная радиостанция.
+    UnbackedSlot { button: ButtonRef, slot: i32 },
+    /// Несколько радиостанций заняли один слот; `stations` - их имена.
+    DuplicateSlot { slot: i32, stations: Vec<String> },
+    /// Радиостанция доступна, но ни одна кнопка радиопанели её не вызывает.
+    UnplacedStation { station: String, slot: i32 },
+}
+
+impl RadioSlotIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            RadioSlotIssue::UnplacedStation { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl std::fmt::Display for RadioSlotIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RadioSlotIssue::UnbackedSlot { button, slot } => write!(
+                f,
+                "{} uses slot {} with no available radiostation",
+                button, slot
+            ),
+            RadioSlotIssue::DuplicateSlot { slot, stations } => write!(
+                f,
+                "slot {} is claimed by several radiostations: {}",
+                slot,
+                stations.join(", ")
+            ),
+            RadioSlotIssue::UnplacedStation { station, slot } if *slot == UNASSIGNED_SLOT => {
+                write!(f, "radiostation {:?} has no slot assigned", station)
+            }
+            RadioSlotIssue::UnplacedStation { station, slot } => write!(
+                f,
+                "radiostation {:?} (slot {}) is not placed on any radio panel",
+                station, slot
+            ),
+        }
+    }
+}
+
+/// Сверяет слоты кнопок радиопанелей со списком доступных радиостанций.
+/// Слот `-1` у радиостанции означает, что она ни к чему не привязана.
+pub fn check_radio_slots(config: &ScreenConfig) -> Vec<RadioSlotIssue> {
+    let mut issues = Vec::new();
+    let stations = &config.available_radiostations;
+    let has_station =
+        |slot: i32| slot != UNASSIGNED_SLOT && stations.iter().any(|station| station.slot == slot);
+    let button_slots: Vec<i32> = config
+        .radio_panels
+        .iter()
+        .flat_map(|panel| panel.buttons.iter().map(|button| button.slot))
+        .collect();
+
+    for panel in &config.radio_panels {
+        for button in panel.buttons.iter().filter(|b| !has_station(b.slot)) {
+            issues.push(RadioSlotIssue::UnbackedSlot {
+                button: ButtonRef {
+                    kind: PanelKind::Radio,
+                    panel: panel.id.clone(),
+                    button: button.id.clone(),
+                },
+                slot: button.slot,
+            });
+        }
+    }
+
+    for (idx, station) in stations.iter().enumerate() {
+        let first = stations.iter().position(|s| s.slot == station.slot);
+        if station.slot == UNASSIGNED_SLOT || first != Some(idx) {
+            continue;
+        }
+        let claimed: Vec<String> = stations
+            .iter()
+            .filter(|s| s.slot == station.slot)
+            .map(|s| s.radio_name.clone())
+            .collect();
+        if claimed.len() > 1 {
+            issues.push(RadioSlotIssue::DuplicateSlot {
+                slot: station.slot,
+                stations: claimed,
+            });
+        }
+    }
+
+    for station in stations {
+        if station.slot == UNASSIGNED_SLOT || !button_slots.contains(&station.slot) {
+            issues.push(RadioSlotIssue::UnplacedStation {
+                station: station.radio_name.clone(),
+                slot: station.slot,
+            });
+        }
+    }
+    issues
+}
+
+// -----------------------------------------------------------------------------
+// phone addresses
+// -----------------------------------------------------------------------------
+
+/// Ошибка в адресах и подписях кнопок телефонных панелей.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddressIssue {
+    /// Кнопка вызывает собственный внутренний номер рабочего места.
+    SelfCall { button: ButtonRef, address: u32 },
+    /// Один и тот же адрес подписан на разных кнопках по-разному.
+    ConflictingLabels {
+        address: u32,
+        buttons: Vec<(ButtonRef, String)>,
+    },
+    /// Адрес кнопки не задан или равен нулю.
+    MissingAddress { button: ButtonRef },
+    /// У кнопки пустая подпись.
+    EmptyText { button: ButtonRef, address: u32 },
+}
+
+impl AddressIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            AddressIssue::ConflictingLabels { .. } | AddressIssue::EmptyText { .. } => {
+                Severity::Warning
+            }
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl std::fmt::Display for AddressIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressIssue::SelfCall { button, address } => write!(
+                f,
+                "{} dials the workstation's own address {}",
+                button, address
+            ),
+            AddressIssue::ConflictingLabels { address, buttons } => {
+                write!(f, "address {} has different labels:", address)?;
+                for (button, text) in buttons {
+                    write!(f, " {} {:?};", button, text)?;
+                }
+                Ok(())
+            }
+            AddressIssue::MissingAddress { button } => {
+                write!(f, "{} has no internal address", button)
+            }
+            AddressIssue::EmptyText { button, address } => {
+                write!(f, "{} (address {}) has empty text", button, address)
+            }
+        }
+    }
+}
+
+/// Проверяет адреса кнопок телефонных панелей: вызов собственного номера,
+/// разные подписи у одного адреса, нулевой адрес и пустую подпись.
+pub fn check_phone_addresses(config: &ScreenConfig) -> Vec<AddressIssue> {
+    let mut issues = Vec::new();
+    let mut by_address: Vec<(u32, Vec<(ButtonRef, String)>)> = Vec::new();
+
+    for panel in &config.phone_panels {
+        for button in &panel.buttons {
+            let button_ref = ButtonRef {
+                kind: PanelKind::Phone,
+                panel: panel.id.clone(),
+                button: button.id.clone(),
+            };
+            let address = button.internal_address;
+            if address == 0 {
+                issues.push(AddressIssue::MissingAddress {
+                    button: button_ref.clone(),
+                });
+            } else if Some(address) == config.internal_address {
+                issues.push(AddressIssue::SelfCall {
+                    button: button_ref.clone(),
+                    address,
+                });
+            }
+            if button.text.trim().is_empty() {
+                issues.push(AddressIssue::EmptyText {
+                    button: button_ref.clone(),
+                    address,
+                });
+            }
+            if address != 0 {
+                let labelled = (button_ref, button.text.clone());
+                match by_address.iter_mut().find(|(a, _)| *a == address) {
+                    Some((_, buttons)) => buttons.push(labelled),
+                    None => by_address.push((address, vec![labelled])),
+                }
+            }
+        }
+    }
+
+    for (address, buttons) in by_address {
+        let first = &buttons[0].1;
+        if buttons.iter().any(|(_, text)| text.trim() != first.trim()) {
+            issues.push(AddressIssue::ConflictingLabels { address, buttons });
+        }
+    }
+    issues
+}
@@ -1,29 +1,39 @@
 #![windows_subsystem = "windows"]
 use eframe::Frame;
-use egui::{CentralPanel, MenuBar, TopBottomPanel, Vec2, ViewportCommand};
-use encoding::{DecoderTrap, Encoding, all::KOI8_R};
-use megafon_cfg_viewer::config::{ScreenConfig, parse};
+use egui::{CentralPanel, MenuBar, SidePanel, TopBottomPanel, Vec2, ViewportCommand};
+use megafon_cfg_viewer::config::{PhoneButton, ScreenConfig, load_path};
+use megafon_cfg_viewer::diff::{self, ButtonChange, ButtonInfo, ConfigDiff, PanelDiff};
+use megafon_cfg_viewer::directory::{Directory, LabelMismatch};
+use megafon_cfg_viewer::fleet::Fleet;
+use megafon_cfg_viewer::lint::{PanelKind, Rect};
 use rfd::FileDialog;
-use std::{fs::File, io::Read};
 
+#[derive(Default)]
 struct AppState {
     screen_cfg: ScreenConfig,
     selected_panel: usize,
     last_error: Option<String>,
+    // second config shown as a comparison of the selected panel
+    compare_cfg: Option<ScreenConfig>,
+    // changes from `screen_cfg` to `compare_cfg`, kept up to date by `compare`
+    compare_diff: ConfigDiff,
+    // all workstations of a site, listed in the sidebar
+    fleet: Option<Fleet>,
+    selected_workstation: Option<usize>,
+    // dangling report of `fleet`, one line per finding; kept up to date by
+    // `check_fleet`
+    dangling: Vec<String>,
+    // addresses labelled differently across the fleet, as report lines, and
+    // how many there are
+    inconsistent_labels: (usize, Vec<String>),
+    // subscriber directory used to annotate buttons and check their labels
+    directory: Option<Directory>,
+    // label mismatches of `screen_cfg` against `directory`, kept up to date
+    // by `check_labels`
+    label_mismatches: Vec<LabelMismatch>,
     // modal_opened: bool,
 }
 
-impl Default for AppState {
-    fn default() -> Self {
-        Self {
-            screen_cfg: ScreenConfig::default(),
-            selected_panel: 0,
-            last_error: None,
-            // modal_opened: false,
-        }
-    }
-}
-
 impl AppState {
     fn open_cfg_via_dialog(&mut self) {
         if let Some(path) = FileDialog::new()
@@ -31,29 +41,301 @@ impl AppState {
             .set_title("Open Megafon config")
             .pick_file()
         {
-            if let Some(p) = path.to_str() {
-                if let Ok(mut f) = File::open(p) {
-                    let mut buffer = Vec::new();
-                    if let Ok(_) = f.read_to_end(&mut buffer) {
-                        match KOI8_R.decode(&buffer, DecoderTrap::Strict) {
-                            Ok(s) => {
-                                if let Ok(c) = parse(&s.trim()) {
-                                    self.screen_cfg = c;
-                                }
-                            }
-                            Err(_) => {}
-                        };
-                    }
+            match load_path(&path) {
+                Ok(loaded) => {
+                    self.screen_cfg = loaded.config;
+                    self.selected_panel = 0;
+                    self.selected_workstation = None;
+                    self.last_error = None;
+                    self.check_labels();
+                    self.compare();
+                }
+                Err(err) => self.last_error = Some(format!("{}: {}", path.display(), err)),
+            }
+        }
+    }
+
+    fn open_fleet_via_dialog(&mut self) {
+        if let Some(dir) = FileDialog::new()
+            .set_title("Open folder with Megafon configs")
+            .pick_folder()
+        {
+            match Fleet::load(&dir) {
+                Ok(fleet) => {
+                    self.fleet = Some(fleet);
+                    self.select_workstation(0);
+                    self.last_error = None;
+                    self.check_fleet();
                 }
+                Err(err) => self.last_error = Some(format!("{}: {}", dir.display(), err)),
+            }
+        }
+    }
+
+    fn select_workstation(&mut self, idx: usize) {
+        let Some(ws) = self.fleet.as_ref().and_then(|f| f.workstations.get(idx)) else {
+            return;
+        };
+        self.screen_cfg = ws.config().clone();
+        self.selected_workstation = Some(idx);
+        self.selected_panel = 0;
+        self.check_labels();
+        self.compare();
+    }
+
+    fn compare_cfg_via_dialog(&mut self) {
+        if let Some(path) = FileDialog::new()
+            .add_filter("CFG files", &["conf"])
+            .set_title("Compare with Megafon config")
+            .pick_file()
+        {
+            match load_path(&path) {
+                Ok(loaded) => {
+                    self.compare_cfg = Some(loaded.config);
+                    self.last_error = None;
+                    self.compare();
+                }
+                Err(err) => self.last_error = Some(format!("{}: {}", path.display(), err)),
+            }
+        }
+    }
+
+    fn import_directory_via_dialog(&mut self) {
+        if let Some(path) = FileDialog::new()
+            .add_filter("CSV files", &["csv"])
+            .set_title("Import subscriber directory")
+            .pick_file()
+        {
+            match Directory::load_path(&path) {
+                Ok(directory) => {
+                    self.directory = Some(directory);
+                    self.last_error = None;
+                    self.check_labels();
+                    self.check_fleet();
+                }
+                Err(err) => self.last_error = Some(format!("{}: {}", path.display(), err)),
             }
         }
     }
 
     fn close_cfg(&mut self) {
         self.screen_cfg = ScreenConfig::default();
+        self.compare_cfg = None;
+        self.fleet = None;
+        self.selected_workstation = None;
         self.selected_panel = 0;
+        self.check_labels();
+        self.check_fleet();
+        self.compare();
+    }
+
+    /// Сравнивает текущую конфигурацию со второй; вызывается при смене любой
+    /// из них, а не на каждом кадре.
+    fn compare(&mut self) {
+        self.compare_diff = self
+            .compare_cfg
+            .as_ref()
+            .map(|other| diff::diff(&self.screen_cfg, other))
+            .unwrap_or_default();
+    }
+
+    /// Сверяет подписи текущей конфигурации со справочником. Вызывается при
+    /// смене конфигурации или справочника, а не на каждом кадре.
+    fn check_labels(&mut self) {
+        self.label_mismatches = self
+            .directory
+            .as_ref()
+            .map(|d| d.check_labels(&self.screen_cfg))
+            .unwrap_or_default();
+    }
+
+    /// Пересчитывает отчёты по площадке для боковой панели: при загрузке
+    /// площадки и смене справочника, а не на каждом кадре.
+    fn check_fleet(&mut self) {
+        let Some(fleet) = &self.fleet else {
+            self.dangling.clear();
+            self.inconsistent_labels = Default::default();
+            return;
+        };
+        let known = self
+            .directory
+            .as_ref()
+            .map(Directory::addresses)
+            .unwrap_or_default();
+        let report = fleet.dangling_report(&known);
+        self.dangling = report.to_string().lines().map(str::to_owned).collect();
+        let labels = fleet.label_report();
+        self.inconsistent_labels = (
+            labels.inconsistent().count(),
+            labels.to_string().lines().map(str::to_owned).collect(),
+        );
+    }
+
+    fn fleet_sidebar(&mut self, ui: &mut egui::Ui) {
+        let Some(fleet) = &self.fleet else {
+            return;
+        };
+        ui.heading("Workstations");
+        ui.separator();
+        let mut clicked = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (idx, ws) in fleet.workstations.iter().enumerate() {
+                let selected = self.selected_workstation == Some(idx);
+                let response = ui
+                    .selectable_label(selected, ws.to_string())
+                    .on_hover_text(fleet.relative_path(&ws.path).display().to_string());
+                if response.clicked() {
+                    clicked = Some(idx);
+                }
+            }
+            if !self.dangling.is_empty() {
+                ui.add_space(8.0);
+                let count = self.dangling.len();
+                egui::CollapsingHeader::new(format!("Dangling addresses ({})", count))
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        for line in &self.dangling {
+                            ui.label(line);
+                        }
+                    });
+            }
+            let (count, lines) = &self.inconsistent_labels;
+            if *count > 0 {
+                ui.add_space(8.0);
+                egui::CollapsingHeader::new(format!("Inconsistent labels ({})", count))
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        for line in lines {
+                            ui.label(line);
+                        }
+                    });
+            }
+            if !fleet.errors.is_empty() {
+                ui.add_space(8.0);
+                let errors: Vec<String> = fleet.errors.iter().map(|e| e.to_string()).collect();
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!("{} file(s) failed to load", errors.len()),
+                )
+                .on_hover_text(errors.join("\n"));
+            }
+        });
+        if let Some(idx) = clicked {
+            self.select_workstation(idx);
+        }
     }
+
+    /// Идентификаторы вкладок: панели открытой конфигурации, а при сравнении
+    /// ещё и панели, которые есть только во второй конфигурации.
+    fn panel_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .screen_cfg
+            .phone_panels
+            .iter()
+            .map(|p| p.id.clone())
+            .collect();
+        if let Some(other) = &self.compare_cfg {
+            for panel in &other.phone_panels {
+                if !ids.contains(&panel.id) {
+                    ids.push(panel.id.clone());
+                }
+            }
+        }
+        ids
+    }
+}
+
+// -----------------------------------------------------------------------------
+// drawing helpers
+// -----------------------------------------------------------------------------
+
+const BUTTON_FILL: egui::Color32 = egui::Color32::from_rgb(40, 140, 40);
+const BUTTON_FILL_HOVERED: egui::Color32 = egui::Color32::from_rgb(40, 200, 40);
+const UNCHANGED_FILL: egui::Color32 = egui::Color32::from_rgb(110, 110, 110);
+const ADDED_FILL: egui::Color32 = egui::Color32::from_rgb(40, 170, 40);
+const REMOVED_FILL: egui::Color32 = egui::Color32::from_rgba_premultiplied(170, 30, 30, 170);
+const CHANGED_FILL: egui::Color32 = egui::Color32::from_rgb(200, 140, 20);
+const MISMATCH_FILL: egui::Color32 = egui::Color32::from_rgb(190, 90, 20);
+
+fn screen_rect(canvas: egui::Rect, rect: &Rect) -> egui::Rect {
+    let x = canvas.left() + rect.x * canvas.width();
+    let y = canvas.top() + rect.y * canvas.height();
+    let w = rect.width * canvas.width();
+    let h = rect.height * canvas.height();
+    egui::Rect::from_min_size(egui::pos2(x, y), Vec2::new(w, h))
 }
+
+fn paint_button(ui: &egui::Ui, rect: egui::Rect, text: &str, fill: egui::Color32) {
+    let painter = ui.painter();
+    painter.rect_filled(rect, 4.0, fill);
+    painter.rect_stroke(
+        rect,
+        4.0,
+        egui::Stroke::new(1.0, egui::Color32::BLACK),
+        egui::StrokeKind::Outside,
+    );
+
+    // Draw button text with word wrapping inside the button rect.
+    // If the text does not fit on one line, it is wrapped to new lines
+    // at word boundaries (handled by egui's layout engine).
+    let padding = egui::vec2(8.0, 8.0);
+    let inner_rect = rect.shrink2(padding);
+    let base_font = egui::TextStyle::Button.resolve(ui.style());
+    let max_width = inner_rect.width().max(0.0);
+    let galley = painter.layout(text.to_string(), base_font, egui::Color32::WHITE, max_width);
+    // Center the (potentially multi-line) text inside the inner rect.
+    let galley_size = galley.size();
+    let text_pos = egui::pos2(
+        inner_rect.center().x - galley_size.x / 2.0,
+        inner_rect.center().y - galley_size.y / 2.0,
+    );
+    painter.galley(text_pos, galley, egui::Color32::WHITE);
+}
+
+/// Пунктирный контур прежнего положения перемещённой или изменённой кнопки.
+fn paint_ghost(ui: &egui::Ui, rect: egui::Rect) {
+    let stroke = egui::Stroke::new(1.5, egui::Color32::from_gray(200));
+    let corners = [
+        rect.left_top(),
+        rect.right_top(),
+        rect.right_bottom(),
+        rect.left_bottom(),
+        rect.left_top(),
+    ];
+    ui.painter()
+        .extend(egui::Shape::dashed_line(&corners, stroke, 6.0, 4.0));
+}
+
+fn change_tooltip(change: &ButtonChange) -> String {
+    match change {
+        ButtonChange::Added(button) => format!("Added: {}", describe(button)),
+        ButtonChange::Removed(button) => format!("Removed: {}", describe(button)),
+        ButtonChange::Changed { old, new } => {
+            let mut lines = Vec::new();
+            if old.id != new.id {
+                lines.push(format!("{} -> {}", old.id, new.id));
+            }
+            if change.relabelled() {
+                lines.push(format!("label: {:?} -> {:?}", old.text, new.text));
+            }
+            if change.retargeted() {
+                lines.push(format!("{} -> {}", old.target, new.target));
+            }
+            if change.moved() {
+                lines.push("moved".to_string());
+            }
+            if change.resized() {
+                lines.push("resized".to_string());
+            }
+            lines.join("\n")
+        }
+    }
+}
+
+fn describe(button: &ButtonInfo) -> String {
+    format!("{} {:?}, {}", button.id, button.text, button.target)
+}
+
 impl eframe::App for AppState {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
         // if self.modal_opened {
@@ -83,6 +365,40 @@ impl eframe::App for AppState {
                         self.open_cfg_via_dialog();
                     }
 
+                    if ui.button("Open folder…").clicked() {
+                        ui.close();
+                        self.open_fleet_via_dialog();
+                    }
+
+                    if ui.button("Import directory…").clicked() {
+                        ui.close();
+                        self.import_directory_via_dialog();
+                    }
+
+                    let loaded = !self.screen_cfg.phone_panels.is_empty();
+                    if ui
+                        .add_enabled(loaded, egui::Button::new("Compare with…"))
+                        .clicked()
+                    {
+                        ui.close();
+                        self.compare_cfg_via_dialog();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.compare_cfg.is_some(),
+                            egui::Button::new("Stop comparing"),
+                        )
+                        .clicked()
+                    {
+                        ui.close();
+                        self.compare_cfg = None;
+                        self.compare();
+                        if self.selected_panel >= self.screen_cfg.phone_panels.len() {
+                            self.selected_panel = 0;
+                        }
+                    }
+
                     if ui.button("Close").clicked() {
                         ui.close();
                         self.close_cfg();
@@ -96,6 +412,14 @@ impl eframe::App for AppState {
             });
         });
 
+        // --- Workstations of the loaded fleet ---
+        if self.fleet.is_some() {
+            SidePanel::left("fleet_sidebar")
+                .resizable(true)
+                .default_width(200.0)
+                .show(ctx, |ui| self.fleet_sidebar(ui));
+        }
+
         // --- Main content area ---
         CentralPanel::default().show(ctx, |ui| {
             if let Some(err) = &self.last_error {
@@ -105,99 +429,77 @@ impl eframe::App for AppState {
 
             if self.screen_cfg.phone_panels.is_empty() {
                 ui.label(
-                    "Use File --> Open… to load a config file and see phone panels with buttons.",
+                    "Use File --> Open… to load a config file (or Open folder… for a whole site) and see phone panels with buttons.",
                 );
                 return;
             }
 
             // --- Tabs (phone panels) ---
+            let panel_ids = self.panel_ids();
             ui.horizontal_wrapped(|ui| {
-                for (idx, panel) in self.screen_cfg.phone_panels.iter().enumerate() {
+                for (idx, id) in panel_ids.iter().enumerate() {
                     let selected = self.selected_panel == idx;
-                    let response = ui.selectable_label(selected, &panel.id);
+                    let response = ui.selectable_label(selected, id);
                     if response.clicked() {
                         self.selected_panel = idx;
                     }
                 }
             });
 
+            if self.compare_cfg.is_some() {
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    ui.label("Comparison:");
+                    ui.colored_label(ADDED_FILL, "added");
+                    ui.colored_label(egui::Color32::from_rgb(220, 60, 60), "removed");
+                    ui.colored_label(CHANGED_FILL, "changed (dashed outline: old geometry)");
+                });
+            }
+
             ui.add_space(8.0);
             ui.separator();
             ui.add_space(8.0);
 
             // --- Canvas with normalized buttons for the selected panel ---
-            if let Some(panel) = self.screen_cfg.phone_panels.get(self.selected_panel) {
+            if let Some(panel_id) = panel_ids.get(self.selected_panel) {
                 ui.add_space(8.0);
 
                 let available_size = ui.available_size();
                 egui::ScrollArea::both().show(ui, |ui| {
                     ui.set_min_size(available_size);
-
-                    let painter = ui.painter().clone();
                     let canvas_rect = ui.max_rect();
-                    let canvas_width = canvas_rect.width();
-                    let canvas_height = canvas_rect.height();
-
-                    for (idx, btn) in panel.buttons.iter().enumerate() {
-                        let x = canvas_rect.left() + btn.position_x * canvas_width;
-                        let y = canvas_rect.top() + btn.position_y * canvas_height;
-                        let w = btn.size_width * canvas_width;
-                        let h = btn.size_height * canvas_height;
-                        let rect = egui::Rect::from_min_size(egui::pos2(x, y), Vec2::new(w, h));
-
-                        let id =
-                            ui.make_persistent_id(format!("btn_{}_{}", self.selected_panel, idx));
-
-                        let response = ui.interact(rect, id, egui::Sense::click());
-
-                        let fill = if response.hovered() {
-                            egui::Color32::from_rgb(40, 200, 40)
-                        } else {
-                            egui::Color32::from_rgb(40, 140, 40)
-                        };
-
-                        painter.rect_filled(rect, 4.0, fill);
-                        painter.rect_stroke(
-                            rect,
-                            4.0,
-                            egui::Stroke::new(1.0, egui::Color32::BLACK),
-                            egui::StrokeKind::Outside,
-                        );
-
-                        // painter.text(
-                        //     rect.center(),
-                        //     egui::Align2::CENTER_CENTER,
-                        //     &btn.text,
-                        //     egui::TextStyle::Button.resolve(ui.style()),
-                        //     egui::Color32::WHITE,
-                        // );
-
-                        // Draw button text with word wrapping inside the button rect.
-                        // If the text does not fit on one line, it is wrapped to new lines
-                        // at word boundaries (handled by egui's layout engine).
-                        let padding = egui::vec2(8.0, 8.0);
-                        let inner_rect = rect.shrink2(padding);
-                        let base_font = egui::TextStyle::Button.resolve(ui.style());
-                        let max_width = inner_rect.width().max(0.0);
-                        let galley = ui.painter().layout(
-                            btn.text.clone(),
-                            base_font.clone(),
-                            egui::Color32::WHITE,
-                            max_width,
-                        );
-                        // Center the (potentially multi-line) text inside the inner rect.
-                        let galley_size = galley.size();
-                        let text_pos = egui::pos2(
-                            inner_rect.center().x - galley_size.x / 2.0,
-                            inner_rect.center().y - galley_size.y / 2.0,
-                        );
-                        painter.galley(text_pos, galley, egui::Color32::WHITE);
-
-                        if response.clicked() {
-                            // self.last_error =
-                            //     format!("Button clicked: [{}] {}", panel.id, btn.text).into();
-                            // self.modal_opened = true;
-                            // eprintln!("Button clicked: [{}] {}", panel.id, btn.text);
+
+                    match &self.compare_cfg {
+                        None => {
+                            let panel = self
+                                .screen_cfg
+                                .phone_panels
+                                .iter()
+                                .find(|p| &p.id == panel_id);
+                            if let Some(panel) = panel {
+                                draw_panel(
+                                    ui,
+                                    canvas_rect,
+                                    self.selected_panel,
+                                    &panel.id,
+                                    &panel.buttons,
+                                    self.directory.as_ref(),
+                                    &self.label_mismatches,
+                                );
+                            }
+                        }
+                        Some(other) => {
+                            let buttons = other
+                                .phone_panels
+                                .iter()
+                                .find(|p| &p.id == panel_id)
+                                .map_or(&[][..], |p| &p.buttons[..]);
+                            draw_comparison(
+                                ui,
+                                canvas_rect,
+                                buttons,
+                                self.compare_diff.panel(PanelKind::Phone, panel_id),
+                            );
                         }
                     }
                 });
@@ -206,6 +508,108 @@ impl eframe::App for AppState {
     }
 }
 
+/// Рисует кнопки телефонной панели. Если загружен справочник абонентов,
+/// подсказка кнопки показывает, кому принадлежит её адрес, а кнопки с
+/// подписью не того абонента выделяются цветом.
+fn draw_panel(
+    ui: &egui::Ui,
+    canvas_rect: egui::Rect,
+    panel_idx: usize,
+    panel_id: &str,
+    buttons: &[PhoneButton],
+    directory: Option<&Directory>,
+    mismatches: &[LabelMismatch],
+) {
+    for (idx, btn) in buttons.iter().enumerate() {
+        let rect = screen_rect(canvas_rect, &Rect::from(btn));
+        let id = ui.make_persistent_id(format!("btn_{}_{}", panel_idx, idx));
+        let response = ui.interact(rect, id, egui::Sense::click());
+        let mismatch = mismatches
+            .iter()
+            .find(|m| m.button.panel == panel_id && m.button.button == btn.id);
+
+        let fill = match (mismatch, response.hovered()) {
+            (Some(_), _) => MISMATCH_FILL,
+            (None, true) => BUTTON_FILL_HOVERED,
+            (None, false) => BUTTON_FILL,
+        };
+        paint_button(ui, rect, &btn.text, fill);
+
+        if let Some(directory) = directory {
+            let mut tooltip = match directory.get(btn.internal_address) {
+                Some(subscriber) => subscriber.to_string(),
+                None => format!("{}: not in the directory", btn.internal_address),
+            };
+            if let Some(mismatch) = mismatch {
+                tooltip += &format!("\n\n{}", mismatch);
+            }
+            response.clone().on_hover_text(tooltip);
+        }
+
+        if response.clicked() {
+            // self.last_error =
+            //     format!("Button clicked: [{}] {}", panel.id, btn.text).into();
+            // self.modal_opened = true;
+            // eprintln!("Button clicked: [{}] {}", panel.id, btn.text);
+        }
+    }
+}
+
+/// Рисует панель второй конфигурации поверх изменений относительно первой:
+/// добавленные кнопки зелёные, удалённые красные, изменённые оранжевые с
+/// пунктирным контуром прежнего положения. Подробности - во всплывающей
+/// подсказке.
+fn draw_comparison(
+    ui: &egui::Ui,
+    canvas_rect: egui::Rect,
+    buttons: &[PhoneButton],
+    changes: Option<&PanelDiff>,
+) {
+    let changes = changes.map_or(&[][..], |panel| &panel.buttons[..]);
+    let change_of = |btn: &PhoneButton| {
+        changes.iter().find(|change| match change {
+            ButtonChange::Added(new) | ButtonChange::Changed { new, .. } => new.id == btn.id,
+            ButtonChange::Removed(_) => false,
+        })
+    };
+
+    for change in changes {
+        if let ButtonChange::Changed { old, .. } = change
+            && (change.moved() || change.resized())
+        {
+            paint_ghost(ui, screen_rect(canvas_rect, &old.rect));
+        }
+    }
+
+    for (idx, btn) in buttons.iter().enumerate() {
+        let rect = screen_rect(canvas_rect, &Rect::from(btn));
+        let id = ui.make_persistent_id(format!("cmp_btn_{}", idx));
+        let response = ui.interact(rect, id, egui::Sense::hover());
+        let change = change_of(btn);
+        let fill = match change {
+            Some(ButtonChange::Added(_)) => ADDED_FILL,
+            Some(_) => CHANGED_FILL,
+            None => UNCHANGED_FILL,
+        };
+        paint_button(ui, rect, &btn.text, fill);
+        if let Some(change) = change {
+            response.on_hover_text(change_tooltip(change));
+        }
+    }
+
+    let removed = changes.iter().filter_map(|change| match change {
+        ButtonChange::Removed(old) => Some((change, old)),
+        _ => None,
+    });
+    for (idx, (change, old)) in removed.enumerate() {
+        let rect = screen_rect(canvas_rect, &old.rect);
+        let id = ui.make_persistent_id(format!("cmp_removed_{}", idx));
+        paint_button(ui, rect, &old.text, REMOVED_FILL);
+        ui.interact(rect, id, egui::Sense::hover())
+            .on_hover_text(change_tooltip(change));
+    }
+}
+
 fn main() -> eframe::Result<()> {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -0,0 +1,493 @@
+use crate::config::{
+    AvailableRadiostation, PhoneButton, PhonePanel, RadioButton, RadioPanel, ScreenConfig,
+};
+use crate::diff::{ButtonInfo, Target, match_buttons, phone_buttons, radio_buttons, same_coord};
+use crate::lint::{PanelKind, Rect};
+
+// -----------------------------------------------------------------------------
+// merge result
+// -----------------------------------------------------------------------------
+
+/// Конфликт слияния: одно и то же место изменено в обеих ветках по-разному.
+/// `None` означает, что в соответствующей версии элемента нет.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub path: String,
+    pub base: Option<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let show = |value: &Option<String>| value.clone().unwrap_or_else(|| "(absent)".to_string());
+        write!(
+            f,
+            "{}: base {}, ours {}, theirs {}",
+            self.path,
+            show(&self.base),
+            show(&self.ours),
+            show(&self.theirs)
+        )
+    }
+}
+
+/// Результат слияния. При конфликтах в `config` оставлена наша версия
+/// спорных мест.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeResult {
+    pub config: ScreenConfig,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl MergeResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// merge logic
+// -----------------------------------------------------------------------------
+
+/// Трёхстороннее слияние конфигураций: `base` - общий предок, `ours` и
+/// `theirs` - две независимые правки.
+///
+/// Панели сопоставляются по идентификатору, кнопки - так же, как в
+/// [`crate::diff::diff`]: по адресу и подписи, а не по номеру `ButtonNN`.
+/// Для каждой кнопки независимо сливаются положение, размер, адрес (слот) и
+/// подпись, поэтому правки разных полей одной кнопки не конфликтуют.
+/// Кнопки, добавленные только в `theirs`, получают свободные номера.
+///
+/// Для использования в качестве драйвера слияния git:
+///
+/// ```text
+/// # .git/config
+/// [merge "megafon"]
+///     name = Megafon console config merge
+///     driver = megafon-cfg merge %O %A %B
+/// # .gitattributes
+/// *.conf merge=megafon
+/// ```
+pub fn merge(base: &ScreenConfig, ours: &ScreenConfig, theirs: &ScreenConfig) -> MergeResult {
+    let mut merger = Merger::default();
+    let config = ScreenConfig {
+        internal_address: merger.value(
+            "internal_address",
+            &base.internal_address,
+            &ours.internal_address,
+            &theirs.internal_address,
+            |a| a.map(|a| a.to_string()),
+        ),
+        name: merger.value("name", &base.name, &ours.name, &theirs.name, |n| {
+            n.as_ref().map(|n| format!("{:?}", n))
+        }),
+        available_radiostations: merger.radiostations(
+            &base.available_radiostations,
+            &ours.available_radiostations,
+            &theirs.available_radiostations,
+        ),
+        phone_panels: merger
+            .panels(
+                PanelKind::Phone,
+                &phone_panels(base),
+                &phone_panels(ours),
+                &phone_panels(theirs),
+            )
+            .into_iter()
+            .map(|(id, buttons)| PhonePanel {
+                id,
+                buttons: buttons.iter().map(to_phone_button).collect(),
+            })
+            .collect(),
+        radio_panels: merger
+            .panels(
+                PanelKind::Radio,
+                &radio_panels(base),
+                &radio_panels(ours),
+                &radio_panels(theirs),
+            )
+            .into_iter()
+            .map(|(id, buttons)| RadioPanel {
+                id,
+                buttons: buttons.iter().map(to_radio_button).collect(),
+            })
+            .collect(),
+    };
+    MergeResult {
+        config,
+        conflicts: merger.conflicts,
+    }
+}
+
+type Panel = (String, Vec<ButtonInfo>);
+
+fn phone_panels(config: &ScreenConfig) -> Vec<Panel> {
+    config
+        .phone_panels
+        .iter()
+        .map(|p| (p.id.clone(), phone_buttons(p)))
+        .collect()
+}
+
+fn radio_panels(config: &ScreenConfig) -> Vec<Panel> {
+    config
+        .radio_panels
+        .iter()
+        .map(|p| (p.id.clone(), radio_buttons(p)))
+        .collect()
+}
+
+fn to_phone_button(info: &ButtonInfo) -> PhoneButton {
+    PhoneButton {
+        id: info.id.clone(),
+        internal_address: match info.target {
+            Target::Address(address) => address,
+            Target::Slot(_) => 0,
+        },
+        position_x: info.rect.x,
+        position_y: info.rect.y,
+        size_height: info.rect.height,
+        size_width: info.rect.width,
+        text: info.text.clone(),
+    }
+}
+
+fn to_radio_button(info: &ButtonInfo) -> RadioButton {
+    RadioButton {
+        id: info.id.clone(),
+        position_x: info.rect.x,
+        position_y: info.rect.y,
+        size_height: info.rect.height,
+        size_width: info.rect.width,
+        slot: match info.target {
+            Target::Slot(slot) => slot,
+            Target::Address(_) => -1,
+        },
+        text: info.text.clone(),
+    }
+}
+
+fn same_position(a: &Rect, b: &Rect) -> bool {
+    same_coord(a.x, b.x) && same_coord(a.y, b.y)
+}
+
+fn same_size(a: &Rect, b: &Rect) -> bool {
+    same_coord(a.width, b.width) && same_coord(a.height, b.height)
+}
+
+fn same_button(a: &ButtonInfo, b: &ButtonInfo) -> bool {
+    a.target == b.target
+        && a.text == b.text
+        && same_position(&a.rect, &b.rect)
+        && same_size(&a.rect, &b.rect)
+}
+
+fn same_buttons(a: &[ButtonInfo], b: &[ButtonInfo]) -> bool {
+    a.len() == b.len()
+        && match_buttons(a, b)
+            .iter()
+            .all(|&(i, j)| same_button(&a[i], &b[j]))
+}
+
+/// Первый свободный идентификатор вида `{prefix}NN`.
+fn next_id<'a>(prefix: &str, taken: impl Iterator<Item = &'a str>) -> String {
+    let max = taken
+        .filter_map(|id| id.strip_prefix(prefix)?.parse::<usize>().ok())
+        .max()
+        .unwrap_or(0);
+    format!("{}{:02}", prefix, max + 1)
+}
+
+#[derive(Default)]
+struct Merger {
+    conflicts: Vec<Conflict>,
+}
+
+impl Merger {
+    fn conflict(
+        &mut self,
+        path: String,
+        base: Option<String>,
+        ours: Option<String>,
+        theirs: Option<String>,
+    ) {
+        self.conflicts.push(Conflict {
+            path,
+            base,
+            ours,
+            theirs,
+        });
+    }
+
+    /// Слияние одного значения: берётся та сторона, которая его изменила.
+    fn value<T: Clone + PartialEq>(
+        &mut self,
+        path: &str,
+        base: &T,
+        ours: &T,
+        theirs: &T,
+        show: impl Fn(&T) -> Option<String>,
+    ) -> T {
+        self.value_by(path, base, ours, theirs, T::eq, show)
+    }
+
+    fn value_by<T: Clone>(
+        &mut self,
+        path: &str,
+        base: &T,
+        ours: &T,
+        theirs: &T,
+        same: impl Fn(&T, &T) -> bool,
+        show: impl Fn(&T) -> Option<String>,
+    ) -> T {
+        if same(ours, base) {
+            theirs.clone()
+        } else if same(theirs, base) || same(ours, theirs) {
+            ours.clone()
+        } else {
+            self.conflict(path.to_string(), show(base), show(ours), show(theirs));
+            ours.clone()
+        }
+    }
+
+    fn radiostations(
+        &mut self,
+        base: &[AvailableRadiostation],
+        ours: &[AvailableRadiostation],
+        theirs: &[AvailableRadiostation],
+    ) -> Vec<AvailableRadiostation> {
+        let find = |list: &[AvailableRadiostation], name: &str| {
+            list.iter().find(|s| s.radio_name == name).map(|s| s.slot)
+        };
+        let show = |slot: &Option<i32>| slot.map(|slot| format!("slot {}", slot));
+        let mut merged: Vec<AvailableRadiostation> = Vec::new();
+
+        for station in ours {
+            let name = &station.radio_name;
+            let slot = self.value(
+                &format!("radiostation {:?}", name),
+                &find(base, name),
+                &Some(station.slot),
+                &find(theirs, name),
+                show,
+            );
+            if let Some(slot) = slot {
+                merged.push(AvailableRadiostation {
+                    slot,
+                    ..station.clone()
+                });
+            }
+        }
+        for station in theirs {
+            let name = &station.radio_name;
+            if find(ours, name).is_some() {
+                continue;
+            }
+            let slot = self.value(
+                &format!("radiostation {:?}", name),
+                &find(base, name),
+                &None,
+                &Some(station.slot),
+                show,
+            );
+            if let Some(slot) = slot {
+                let id = next_id(
+                    "AvailableRadiostation",
+                    merged.iter().map(|s| s.id.as_str()),
+                );
+                merged.push(AvailableRadiostation {
+                    id,
+                    radio_name: name.clone(),
+                    slot,
+                });
+            }
+        }
+        merged
+    }
+
+    fn panels(
+        &mut self,
+        kind: PanelKind,
+        base: &[Panel],
+        ours: &[Panel],
+        theirs: &[Panel],
+    ) -> Vec<Panel> {
+        let find = |list: &[Panel], id: &str| {
+            list.iter()
+                .find(|(other, _)| other == id)
+                .map(|(_, buttons)| buttons.clone())
+        };
+        let show = |buttons: &Option<Vec<ButtonInfo>>| {
+            buttons
+                .as_ref()
+                .map(|buttons| format!("{} buttons", buttons.len()))
+        };
+        let mut merged = Vec::new();
+
+        for (id, our_buttons) in ours {
+            let path = format!("{} {}", kind, id);
+            let buttons = match (find(base, id), find(theirs, id)) {
+                (Some(base_buttons), Some(their_buttons)) => {
+                    Some(self.buttons(&path, &base_buttons, our_buttons, &their_buttons))
+                }
+                (base_buttons, their_buttons) => self.value_by(
+                    &path,
+                    &base_buttons,
+                    &Some(our_buttons.clone()),
+                    &their_buttons,
+                    |a, b| match (a, b) {
+                        (Some(a), Some(b)) => same_buttons(a, b),
+                        (None, None) => true,
+                        _ => false,
+                    },
+                    show,
+                ),
+            };
+            if let Some(buttons) = buttons {
+                merged.push((id.clone(), buttons));
+            }
+        }
+        for (id, their_buttons) in theirs {
+            if find(ours, id).is_some() {
+                continue;
+            }
+            let base_buttons = find(base, id);
+            let unchanged = base_buttons
+                .as_ref()
+                .is_some_and(|base_buttons| same_buttons(base_buttons, their_buttons));
+            if base_buttons.is_none() {
+                merged.push((id.clone(), their_buttons.clone()));
+            } else if !unchanged {
+                self.conflict(
+                    format!("{} {}", kind, id),
+                    show(&base_buttons),
+                    None,
+                    show(&Some(their_buttons.clone())),
+                );
+            }
+        }
+        merged
+    }
+
+    fn buttons(
+        &mut self,
+        panel: &str,
+        base: &[ButtonInfo],
+        ours: &[ButtonInfo],
+        theirs: &[ButtonInfo],
+    ) -> Vec<ButtonInfo> {
+        let to_ours = match_buttons(base, ours);
+        let to_theirs = match_buttons(base, theirs);
+        let matched = |pairs: &[(usize, usize)], i: usize| {
+            pairs.iter().find(|&&(b, _)| b == i).map(|&(_, j)| j)
+        };
+        let show = |button: &ButtonInfo| Some(format!("{} {:?}", button.target, button.text));
+
+        let mut merged: Vec<Option<ButtonInfo>> = ours.iter().cloned().map(Some).collect();
+        for (i, base_button) in base.iter().enumerate() {
+            let path = format!("{} {} {:?}", panel, base_button.id, base_button.text);
+            match (matched(&to_ours, i), matched(&to_theirs, i)) {
+                (Some(o), Some(t)) => {
+                    merged[o] = Some(self.button(&path, base_button, &ours[o], &theirs[t]));
+                }
+                (Some(o), None) => {
+                    if same_button(base_button, &ours[o]) {
+                        merged[o] = None;
+                    } else {
+                        self.conflict(path, show(base_button), show(&ours[o]), None);
+                    }
+                }
+                (None, Some(t)) => {
+                    if !same_button(base_button, &theirs[t]) {
+                        self.conflict(path, show(base_button), None, show(&theirs[t]));
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+
+        let mut merged: Vec<ButtonInfo> = merged.into_iter().flatten().collect();
+        let our_added: Vec<&ButtonInfo> = ours
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| !to_ours.iter().any(|&(_, o)| o == j))
+            .map(|(_, button)| button)
+            .collect();
+        for (j, button) in theirs.iter().enumerate() {
+            if to_theirs.iter().any(|&(_, t)| t == j) {
+                continue;
+            }
+            let twin = our_added
+                .iter()
+                .find(|b| b.target == button.target && b.text == button.text);
+            match twin {
+                Some(twin) if same_button(twin, button) => {}
+                Some(twin) => self.conflict(
+                    format!("{} added {:?}", panel, button.text),
+                    None,
+                    Some(format!("at {:?}", twin.rect)),
+                    Some(format!("at {:?}", button.rect)),
+                ),
+                None => {
+                    let id = next_id("Button", merged.iter().map(|b| b.id.as_str()));
+                    merged.push(ButtonInfo {
+                        id,
+                        ..button.clone()
+                    });
+                }
+            }
+        }
+        merged
+    }
+
+    fn button(
+        &mut self,
+        path: &str,
+        base: &ButtonInfo,
+        ours: &ButtonInfo,
+        theirs: &ButtonInfo,
+    ) -> ButtonInfo {
+        let position = self.value_by(
+            &format!("{} position", path),
+            &base.rect,
+            &ours.rect,
+            &theirs.rect,
+            same_position,
+            |r| Some(format!("({:.3}, {:.3})", r.x, r.y)),
+        );
+        let size = self.value_by(
+            &format!("{} size", path),
+            &base.rect,
+            &ours.rect,
+            &theirs.rect,
+            same_size,
+            |r| Some(format!("{:.3}x{:.3}", r.width, r.height)),
+        );
+        let target = self.value(
+            &format!("{} target", path),
+            &base.target,
+            &ours.target,
+            &theirs.target,
+            |t| Some(t.to_string()),
+        );
+        let text = self.value(
+            &format!("{} text", path),
+            &base.text,
+            &ours.text,
+            &theirs.text,
+            |t| Some(format!("{:?}", t)),
+        );
+        ButtonInfo {
+            id: ours.id.clone(),
+            text,
+            target,
+            rect: Rect {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+            },
+        }
+    }
+}
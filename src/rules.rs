@@ -0,0 +1,462 @@
+use crate::config::{ScreenConfig, Severity, delocalize_panel_label};
+use crate::lint::{self, AddressIssue, ButtonRef, LayoutIssue, PanelKind, RadioSlotIssue};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+// -----------------------------------------------------------------------------
+// rule trait
+// -----------------------------------------------------------------------------
+
+/// Замечание одного правила. `buttons` - кнопки, к которым оно относится
+/// (пусто, если замечание касается конфигурации целиком).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub message: String,
+    pub buttons: Vec<ButtonRef>,
+}
+
+/// Правило проверки конфигурации.
+///
+/// Идентификатор правила (`layout-overlap`, `phone-self-call`, ...) стабилен:
+/// по нему правило настраивается в файле проекта и подавляется для отдельных
+/// кнопок. Собственные правила площадки добавляются через [`Linter::add_rule`].
+pub trait Rule {
+    fn id(&self) -> &str;
+    fn description(&self) -> &str;
+    fn default_severity(&self) -> Severity;
+    fn check(&self, config: &ScreenConfig) -> Vec<Finding>;
+}
+
+/// Сообщение линтера: замечание правила с итоговой серьёзностью.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintMessage {
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+    pub buttons: Vec<ButtonRef>,
+}
+
+impl std::fmt::Display for LintMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}[{}]: {}", label, self.rule, self.message)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// built-in rules
+// -----------------------------------------------------------------------------
+
+/// Проход модуля [`lint`]. Один проход находит замечания сразу нескольких
+/// встроенных правил, поэтому линтер выполняет его один раз и раскладывает
+/// замечания по идентификаторам.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Check {
+    Layout,
+    RadioSlots,
+    PhoneAddresses,
+}
+
+impl Check {
+    const ALL: [Check; 3] = [Check::Layout, Check::RadioSlots, Check::PhoneAddresses];
+
+    /// Замечания всех правил прохода вместе с идентификатором правила.
+    fn run(self, config: &ScreenConfig) -> Vec<(&'static str, Finding)> {
+        match self {
+            Check::Layout => findings(lint::check_layout(config)),
+            Check::RadioSlots => findings(lint::check_radio_slots(config)),
+            Check::PhoneAddresses => findings(lint::check_phone_addresses(config)),
+        }
+    }
+}
+
+struct BuiltinRule {
+    id: &'static str,
+    description: &'static str,
+    severity: Severity,
+    check: Check,
+}
+
+impl Rule for BuiltinRule {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn default_severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn check(&self, config: &ScreenConfig) -> Vec<Finding> {
+        self.check
+            .run(config)
+            .into_iter()
+            .filter(|(id, _)| *id == self.id)
+            .map(|(_, finding)| finding)
+            .collect()
+    }
+}
+
+/// Замечание встроенной проверки из модуля [`lint`], привязанное к правилу.
+trait Issue: std::fmt::Display {
+    fn rule_id(&self) -> &'static str;
+    fn buttons(&self) -> Vec<ButtonRef>;
+}
+
+impl Issue for LayoutIssue {
+    fn rule_id(&self) -> &'static str {
+        match self {
+            LayoutIssue::Overlap { .. } => "layout-overlap",
+            LayoutIssue::NegativeSize { .. } => "layout-negative-size",
+            LayoutIssue::OutOfBounds { .. } => "layout-out-of-bounds",
+        }
+    }
+
+    fn buttons(&self) -> Vec<ButtonRef> {
+        match self {
+            LayoutIssue::Overlap { first, second, .. } => vec![first.clone(), second.clone()],
+            LayoutIssue::NegativeSize { button, .. } | LayoutIssue::OutOfBounds { button, .. } => {
+                vec![button.clone()]
+            }
+        }
+    }
+}
+
+impl Issue for RadioSlotIssue {
+    fn rule_id(&self) -> &'static str {
+        match self {
+            RadioSlotIssue::UnbackedSlot { .. } => "radio-unbacked-slot",
+            RadioSlotIssue::DuplicateSlot { .. } => "radio-duplicate-slot",
+            RadioSlotIssue::UnplacedStation { .. } => "radio-unplaced-station",
+        }
+    }
+
+    fn buttons(&self) -> Vec<ButtonRef> {
+        match self {
+            RadioSlotIssue::UnbackedSlot { button, .. } => vec![button.clone()],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Issue for AddressIssue {
+    fn rule_id(&self) -> &'static str {
+        match self {
+            AddressIssue::SelfCall { .. } => "phone-self-call",
+            AddressIssue::ConflictingLabels { .. } => "phone-conflicting-labels",
+            AddressIssue::MissingAddress { .. } => "phone-missing-address",
+            AddressIssue::EmptyText { .. } => "phone-empty-text",
+        }
+    }
+
+    fn buttons(&self) -> Vec<ButtonRef> {
+        match self {
+            AddressIssue::ConflictingLabels { buttons, .. } => {
+                buttons.iter().map(|(button, _)| button.clone()).collect()
+            }
+            AddressIssue::SelfCall { button, .. }
+            | AddressIssue::MissingAddress { button }
+            | AddressIssue::EmptyText { button, .. } => vec![button.clone()],
+        }
+    }
+}
+
+fn findings<I: Issue>(issues: Vec<I>) -> Vec<(&'static str, Finding)> {
+    issues
+        .into_iter()
+        .map(|issue| {
+            let finding = Finding {
+                message: issue.to_string(),
+                buttons: issue.buttons(),
+            };
+            (issue.rule_id(), finding)
+        })
+        .collect()
+}
+
+macro_rules! builtin {
+    ($id:literal, $severity:ident, $check:ident, $description:literal) => {
+        BuiltinRule {
+            id: $id,
+            description: $description,
+            severity: Severity::$severity,
+            check: Check::$check,
+        }
+    };
+}
+
+/// Встроенные правила: проверки раскладки, слотов радиостанций и адресов.
+fn builtins() -> Vec<BuiltinRule> {
+    vec![
+        builtin!(
+            "layout-overlap",
+            Error,
+            Layout,
+            "Buttons of the same panel overlap"
+        ),
+        builtin!(
+            "layout-negative-size",
+            Error,
+            Layout,
+            "Button has a negative width or height"
+        ),
+        builtin!(
+            "layout-out-of-bounds",
+            Error,
+            Layout,
+            "Button lies outside the 0..1 screen bounds"
+        ),
+        builtin!(
+            "radio-unbacked-slot",
+            Error,
+            RadioSlots,
+            "Radio button slot has no available radiostation"
+        ),
+        builtin!(
+            "radio-duplicate-slot",
+            Error,
+            RadioSlots,
+            "Several radiostations claim the same slot"
+        ),
+        builtin!(
+            "radio-unplaced-station",
+            Warning,
+            RadioSlots,
+            "Available radiostation is not placed on any radio panel"
+        ),
+        builtin!(
+            "phone-self-call",
+            Error,
+            PhoneAddresses,
+            "Phone button dials the workstation's own address"
+        ),
+        builtin!(
+            "phone-conflicting-labels",
+            Warning,
+            PhoneAddresses,
+            "Same address is labelled differently on several buttons"
+        ),
+        builtin!(
+            "phone-missing-address",
+            Error,
+            PhoneAddresses,
+            "Phone button has a zero or missing address"
+        ),
+        builtin!(
+            "phone-empty-text",
+            Warning,
+            PhoneAddresses,
+            "Phone button has empty text"
+        ),
+    ]
+}
+
+/// Встроенные правила как самостоятельные [`Rule`]. Каждое из них выполняет
+/// свой проход целиком; [`Linter`] выполняет каждый проход один раз на все
+/// правила.
+pub fn builtin_rules() -> Vec<Box<dyn Rule>> {
+    builtins()
+        .into_iter()
+        .map(|rule| Box::new(rule) as Box<dyn Rule>)
+        .collect()
+}
+
+// -----------------------------------------------------------------------------
+// project file
+// -----------------------------------------------------------------------------
+
+/// Имя файла настроек линтера, который ищется в текущем каталоге.
+pub const PROJECT_FILE: &str = "megafon-lint.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    #[serde(alias = "warning")]
+    Warn,
+    Error,
+}
+
+/// Подавление замечаний для одной кнопки. Без `rule` подавляются все правила,
+/// без `kind` - кнопки панелей обоих видов. Панель задаётся так, как она
+/// записана в файле (`Panel01`) или показана в просмотрщике (`ПД 1`).
+/// Замечание о нескольких кнопках подавляется, только если подавлены все.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Suppression {
+    pub rule: Option<String>,
+    pub kind: Option<PanelKind>,
+    pub panel: String,
+    pub button: String,
+}
+
+impl Suppression {
+    fn matches(&self, rule: &str, button: &ButtonRef) -> bool {
+        self.rule.as_deref().is_none_or(|r| r == rule)
+            && self.kind.is_none_or(|kind| kind == button.kind)
+            && (self.panel == button.panel || self.panel == delocalize_panel_label(&button.panel))
+            && self.button == button.button
+    }
+}
+
+/// Настройки линтера из файла проекта:
+///
+/// ```toml
+/// [rules]
+/// phone-empty-text = "off"
+/// radio-unplaced-station = "error"
+///
+/// [[suppress]]
+/// rule = "layout-overlap"
+/// panel = "Panel03"
+/// button = "Button12"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    pub rules: BTreeMap<String, RuleLevel>,
+    pub suppress: Vec<Suppression>,
+}
+
+#[derive(Error, Debug)]
+pub enum LintConfigError {
+    #[error("Failed to read lint config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid lint config: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+impl LintConfig {
+    pub fn from_toml(input: &str) -> Result<Self, LintConfigError> {
+        Ok(toml::from_str(input)?)
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, LintConfigError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// linter
+// -----------------------------------------------------------------------------
+
+/// Набор правил с настройками серьёзности и подавлениями.
+pub struct Linter {
+    builtins: Vec<BuiltinRule>,
+    rules: Vec<Box<dyn Rule>>,
+    config: LintConfig,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new(LintConfig::default())
+    }
+}
+
+impl Linter {
+    /// Линтер со всеми встроенными правилами.
+    pub fn new(config: LintConfig) -> Self {
+        Linter {
+            builtins: builtins(),
+            rules: Vec::new(),
+            config,
+        }
+    }
+
+    pub fn add_rule(&mut self, rule: Box<dyn Rule>) {
+        self.rules.push(rule);
+    }
+
+    /// Встроенные правила, затем добавленные через [`Linter::add_rule`].
+    pub fn rules(&self) -> impl Iterator<Item = &dyn Rule> {
+        let builtins = self.builtins.iter().map(|rule| rule as &dyn Rule);
+        builtins.chain(self.rules.iter().map(|rule| rule.as_ref()))
+    }
+
+    /// Идентификаторы из файла проекта, которым не соответствует ни одно
+    /// правило (скорее всего, опечатки).
+    pub fn unknown_rule_ids(&self) -> Vec<&str> {
+        let suppressed = self.config.suppress.iter().filter_map(|s| s.rule.as_ref());
+        self.config
+            .rules
+            .keys()
+            .chain(suppressed)
+            .filter(|id| !self.rules().any(|rule| rule.id() == id.as_str()))
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn severity(&self, rule: &dyn Rule) -> Option<Severity> {
+        match self.config.rules.get(rule.id()) {
+            None => Some(rule.default_severity()),
+            Some(RuleLevel::Off) => None,
+            Some(RuleLevel::Warn) => Some(Severity::Warning),
+            Some(RuleLevel::Error) => Some(Severity::Error),
+        }
+    }
+
+    /// Замечание подавлено, только если подавлены все его кнопки: замечание
+    /// о паре кнопок (наложение, разные подписи) остаётся актуальным, пока
+    /// хотя бы одна из них не разобрана. Замечания о конфигурации целиком
+    /// не подавляются.
+    fn is_suppressed(&self, rule: &str, finding: &Finding) -> bool {
+        !finding.buttons.is_empty()
+            && finding
+                .buttons
+                .iter()
+                .all(|button| self.config.suppress.iter().any(|s| s.matches(rule, button)))
+    }
+
+    fn push(
+        &self,
+        messages: &mut Vec<LintMessage>,
+        rule: &str,
+        severity: Severity,
+        finding: Finding,
+    ) {
+        if !self.is_suppressed(rule, &finding) {
+            messages.push(LintMessage {
+                rule: rule.to_string(),
+                severity,
+                message: finding.message,
+                buttons: finding.buttons,
+            });
+        }
+    }
+
+    /// Применяет все включённые правила к конфигурации.
+    pub fn run(&self, config: &ScreenConfig) -> Vec<LintMessage> {
+        let enabled: Vec<_> = self
+            .builtins
+            .iter()
+            .filter_map(|rule| Some((rule, self.severity(rule)?)))
+            .collect();
+        let found: Vec<_> = Check::ALL
+            .into_iter()
+            .filter(|check| enabled.iter().any(|(rule, _)| rule.check == *check))
+            .flat_map(|check| check.run(config))
+            .collect();
+
+        let mut messages = Vec::new();
+        for (rule, severity) in enabled {
+            for (_, finding) in found.iter().filter(|(id, _)| *id == rule.id) {
+                self.push(&mut messages, rule.id, severity, finding.clone());
+            }
+        }
+        for rule in &self.rules {
+            let Some(severity) = self.severity(rule.as_ref()) else {
+                continue;
+            };
+            for finding in rule.check(config) {
+                self.push(&mut messages, rule.id(), severity, finding);
+            }
+        }
+        messages
+    }
+}
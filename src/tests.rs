@@ -1,4 +1,22 @@
-use crate::config::parse;
+use crate::bracket::{self, BracketError};
+use crate::callgraph::CallGraph;
+use crate::charset::{SubstitutionPolicy, TextEncoding, decode_lossy, detect_encoding};
+use crate::config::{
+    ConfigError, EncodeError, ParseOptions, PhoneButton, RadioButton, RadioPanel, ScreenConfig,
+    Severity, encode_config, load_bytes, parse, parse_lenient, parse_with_options, serialize,
+    update_document,
+};
+use crate::diff::{self, ButtonChange};
+use crate::directory::{Directory, DirectoryError};
+use crate::fleet::Fleet;
+use crate::formats::{self, StructuredFormat};
+use crate::generic::{Document, Node, StructureIssue, TreeOptions};
+use crate::lint::{self, AddressIssue, LayoutIssue, PanelKind, RadioSlotIssue};
+use crate::merge;
+use crate::rules::{Finding, LintConfig, Linter, Rule};
+use encoding::all::{KOI8_R, WINDOWS_1251};
+use encoding::{EncoderTrap, Encoding};
+use serde::{Deserialize, Serialize};
 
 const INPUT: &str = r#"
 internal_address = 331
@@ -58,6 +76,24 @@ text = ""
 [#RadioPanels]
 "#;
 
+/// Конфигурация только с телефонными панелями: без `[AvailableRadiostations]`
+/// и `[RadioPanels]`.
+const PHONE_ONLY: &str = "internal_address = 331
+name = \"Инженер КСРС\"
+[PhonePanels]
+[Panel01]
+[Button01]
+internal_address = 303
+position_x = 0.02
+position_y = 0.016
+size_height = 0.147
+size_width = 0.225
+text = \"С-6 ПУ\"
+[#Button01]
+[#Panel01]
+[#PhonePanels]
+";
+
 #[test]
 fn test_parse_screen_globals() {
     let result = parse(INPUT);
@@ -124,3 +160,1282 @@ fn test_parse_screen_radio_panels() {
     assert_eq!(rp1.buttons.len(), 1);
     assert_eq!(rp1.buttons[0].slot, 5);
 }
+
+#[test]
+fn test_serialize_round_trip() {
+    let config = parse(INPUT).unwrap();
+    let text = serialize(&config);
+    let reparsed = parse(&text).unwrap();
+    assert_eq!(reparsed, config);
+}
+
+#[test]
+fn test_serialize_console_layout() {
+    let config = parse(INPUT).unwrap();
+    let text = serialize(&config);
+    let lines: Vec<&str> = text.lines().collect();
+
+    assert_eq!(lines[0], "internal_address = 331");
+    assert_eq!(lines[1], "name = \"Инженер КСРС\"");
+    assert_eq!(lines[2], "[AvailableRadiostations]");
+    assert!(text.contains(
+        "[Panel02]\n[Button01]\ninternal_address = 338\nposition_x = 0.02\nposition_y = 0.016\n\
+         size_height = 0.147\nsize_width = 0.225\ntext = \"Диспетчер ПИВП вне ВТ\"\n[#Button01]\n[#Panel02]\n"
+    ));
+    assert!(text.ends_with("[#RadioPanels]\n"));
+}
+
+#[test]
+fn test_document_round_trip_is_lossless() {
+    let doc = Document::parse(INPUT);
+    assert_eq!(doc.to_string(), INPUT);
+
+    let crlf = INPUT.replace('\n', "\r\n");
+    assert_eq!(Document::parse(&crlf).to_string(), crlf);
+}
+
+#[test]
+fn test_update_document_touches_only_changed_lines() {
+    let mut doc = Document::parse(INPUT);
+    let mut config = parse(INPUT).unwrap();
+
+    update_document(&mut doc, &config);
+    assert_eq!(doc.to_string(), INPUT);
+
+    config.phone_panels[0].buttons[1].text = "С-10 ПУ".to_string();
+    update_document(&mut doc, &config);
+    let saved = doc.to_string();
+
+    assert_eq!(
+        saved,
+        INPUT.replace("text = \"С-9 ПУ\"", "text = \"С-10 ПУ\"")
+    );
+    assert!(saved.contains("master_volume_show = 1"));
+    assert!(saved.contains("    [AvailableRadiostations]"));
+}
+
+#[test]
+fn test_update_document_without_final_newline() {
+    // Последняя строка файла без перевода строки: новые секции и записи
+    // начинаются с новой строки, а не дописываются к ней.
+    let input = PHONE_ONLY.trim_end();
+    let mut doc = Document::parse(input);
+    let mut config = parse(input).unwrap();
+    config.radio_panels.push(RadioPanel {
+        id: "Panel01".to_string(),
+        buttons: vec![RadioButton {
+            id: "Button01".to_string(),
+            slot: 1,
+            ..Default::default()
+        }],
+    });
+    update_document(&mut doc, &config);
+    let text = doc.to_string();
+    assert!(
+        text.contains("[#PhonePanels]\n[RadioPanels]\n[Panel01]\n"),
+        "{text}"
+    );
+    assert_eq!(parse(&text).unwrap(), config);
+
+    let mut doc = Document::parse("internal_address = 331");
+    let mut config = parse("internal_address = 331").unwrap();
+    config.name = Some("N".to_string());
+    update_document(&mut doc, &config);
+    assert_eq!(doc.to_string(), "internal_address = 331\nname = \"N\"\n");
+    assert_eq!(parse(&doc.to_string()).unwrap(), config);
+}
+
+#[test]
+fn test_phone_panels_above_nine_keep_their_numbers() {
+    let input = PHONE_ONLY
+        .replace("[Panel01]", "[Panel10]")
+        .replace("[#Panel01]", "[#Panel10]")
+        .replace("[#PhonePanels]", "[Panel11]\n[#Panel11]\n[#PhonePanels]");
+    let config = parse(&input).unwrap();
+    let ids: Vec<&str> = config.phone_panels.iter().map(|p| p.id.as_str()).collect();
+    assert_eq!(ids, vec!["ПД 10", "ПД 11"]);
+
+    let saved = serialize(&config);
+    assert!(saved.contains("[Panel10]") && saved.contains("[Panel11]"));
+    assert_eq!(parse(&saved).unwrap(), config);
+    let mut doc = Document::parse(&input);
+    update_document(&mut doc, &config);
+    assert_eq!(doc.to_string(), input);
+}
+
+#[test]
+fn test_quotes_inside_labels_round_trip() {
+    let mut config = parse(PHONE_ONLY).unwrap();
+    config.name = Some("\"Инженер\"".to_string());
+    config.phone_panels[0].buttons[0].text = "КСРС \"инж\"".to_string();
+
+    let saved = serialize(&config);
+    assert!(saved.contains("text = \"КСРС \"инж\"\"\n"));
+    assert_eq!(parse(&saved).unwrap(), config);
+
+    let mut doc = Document::parse(PHONE_ONLY);
+    update_document(&mut doc, &config);
+    assert_eq!(parse(&doc.to_string()).unwrap(), config);
+}
+
+#[test]
+fn test_update_document_keeps_missing_sections_missing() {
+    let mut doc = Document::parse(PHONE_ONLY);
+    let mut config = parse(PHONE_ONLY).unwrap();
+
+    update_document(&mut doc, &config);
+    assert_eq!(doc.to_string(), PHONE_ONLY);
+
+    config.phone_panels[0].buttons[0].internal_address = 403;
+    update_document(&mut doc, &config);
+    assert_eq!(
+        doc.to_string(),
+        PHONE_ONLY.replace("internal_address = 303", "internal_address = 403")
+    );
+}
+
+#[test]
+fn test_update_document_adds_and_removes_sections() {
+    let mut doc = Document::parse(INPUT);
+    let mut config = parse(INPUT).unwrap();
+
+    config.phone_panels[0].buttons.remove(0);
+    config.phone_panels[1].buttons.push(PhoneButton {
+        id: "Button02".to_string(),
+        internal_address: 340,
+        text: "Новая".to_string(),
+        ..Default::default()
+    });
+    update_document(&mut doc, &config);
+    let saved = doc.to_string();
+
+    assert!(!saved.contains("internal_address = 303"));
+    assert!(saved.contains(
+        "[#Button01]\n[Button02]\ninternal_address = 340\nposition_x = 0\nposition_y = 0\n\
+         size_height = 0\nsize_width = 0\ntext = \"Новая\"\n[#Button02]\n[#Panel02]"
+    ));
+    assert_eq!(parse(&saved).unwrap(), config);
+}
+
+#[test]
+fn test_parse_error_location() {
+    let broken = INPUT.replace(
+        "position_y = 0.016\nsize_height = 0.147\nsize_width = 0.225\ntext = \"С-9 ПУ\"",
+        "position_y = 0,016\nsize_height = 0.147\nsize_width = 0.225\ntext = \"С-9 ПУ\"",
+    );
+    let err = parse(&broken).unwrap_err();
+    assert!(matches!(err, ConfigError::InvalidFloat { .. }));
+
+    let location = err.location();
+    assert_eq!(location.line, 28);
+    assert_eq!(location.column, 14);
+    assert_eq!(location.raw, "position_y = 0,016");
+    assert_eq!(location.path, "PhonePanels/Panel01/Button02/position_y");
+}
+
+#[test]
+fn test_parse_lenient_collects_all_errors() {
+    let broken = INPUT
+        .replace("internal_address = 303", "internal_address = 3O3")
+        .replace("slot = 5", "slot = five")
+        .replace("[#Panel02]", "garbage line\n[#Panel02]");
+    assert!(parse(&broken).is_err());
+
+    let report = parse_lenient(&broken);
+    assert!(report.has_errors());
+
+    let errors: Vec<_> = report
+        .diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .collect();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        errors[0].error.location().path,
+        "PhonePanels/Panel01/Button01/internal_address"
+    );
+    assert_eq!(
+        errors[1].error.location().path,
+        "RadioPanels/Panel01/Button01/slot"
+    );
+
+    let warnings: Vec<_> = report
+        .diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Warning)
+        .collect();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].error.location().raw, "garbage line");
+
+    // Всё остальное разобрано как обычно.
+    let config = report.config;
+    assert_eq!(config.phone_panels[0].buttons[0].internal_address, 0);
+    assert_eq!(config.phone_panels[0].buttons[1].internal_address, 309);
+    assert_eq!(config.phone_panels[1].buttons.len(), 1);
+    assert_eq!(config.radio_panels[0].buttons[0].slot, 0);
+}
+
+#[test]
+fn test_parse_strict_accepts_well_formed_config() {
+    let strict = ParseOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let report = parse_with_options(INPUT, strict).unwrap();
+    assert!(report.diagnostics.is_empty());
+}
+
+#[test]
+fn test_parse_strict_reports_structural_problems() {
+    let broken = INPUT
+        .replace("master_volume_show = 1", "master_volume = 1")
+        .replace("text = \"С-9 ПУ\"\n", "txt = \"С-9 ПУ\"\n")
+        .replace(
+            "[#PhonePanels]",
+            "[Extra]\nfoo = 1\n[#Extra]\n[#PhonePanels]",
+        );
+    assert!(parse(&broken).is_ok());
+
+    let options = ParseOptions {
+        strict: true,
+        recover: true,
+    };
+    let report = parse_with_options(&broken, options).unwrap();
+    let found: Vec<(String, &str)> = report
+        .diagnostics
+        .iter()
+        .map(|d| {
+            let kind = match &d.error {
+                ConfigError::UnknownGlobalKey { .. } => "unknown global key",
+                ConfigError::UnknownKey { .. } => "unknown key",
+                ConfigError::UnexpectedSection { .. } => "unexpected section",
+                ConfigError::MissingField { .. } => "missing field",
+                _ => "other",
+            };
+            (d.error.location().path.clone(), kind)
+        })
+        .collect();
+
+    assert_eq!(
+        found,
+        vec![
+            ("master_volume".to_string(), "unknown global key"),
+            (
+                "PhonePanels/Panel01/Button02/txt".to_string(),
+                "unknown key"
+            ),
+            (
+                "PhonePanels/Panel01/Button02/text".to_string(),
+                "missing field"
+            ),
+            ("PhonePanels/Extra".to_string(), "unexpected section"),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_lenient_leaves_invalid_global_address_unset() {
+    let broken = INPUT.replacen("internal_address = 331", "internal_address = 33l", 1);
+    let report = parse_lenient(&broken);
+    assert_eq!(report.config.internal_address, None);
+    assert_eq!(report.diagnostics.len(), 1);
+    assert!(matches!(
+        &report.diagnostics[0].error,
+        ConfigError::InvalidInt { location, .. } if location.path == "internal_address"
+    ));
+    assert_eq!(report.config.name, Some("Инженер КСРС".to_string()));
+
+    // При сохранении строка с некорректным адресом не пропадает.
+    let mut doc = Document::parse(&broken);
+    update_document(&mut doc, &report.config);
+    assert_eq!(doc.to_string(), broken);
+}
+
+#[test]
+fn test_parse_detects_unclosed_and_mismatched_sections() {
+    let broken = INPUT
+        .replacen("[#Button01]\n[Button02]", "[#Buton01]\n[Button02]", 1)
+        .replacen("text = \"С-9 ПУ\"\n[#Button02]\n", "text = \"С-9 ПУ\"\n", 1);
+    let err = parse(&broken).unwrap_err();
+    assert!(matches!(err, ConfigError::StrayClosingTag { .. }));
+
+    let report = parse_lenient(&broken);
+    let errors: Vec<String> = report
+        .diagnostics
+        .iter()
+        .map(|d| d.error.to_string())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "Closing tag without an open section at line 24, column 1 \
+             (PhonePanels/Panel01/Button01): `[#Buton01]`",
+            "Section [Button01] opened at line 17, column 2 \
+             (PhonePanels/Panel01/Button01): `[Button01]` is never closed",
+            "Section [Button02] opened at line 25, column 2 \
+             (PhonePanels/Panel01/Button02): `[Button02]` is closed by [#Panel01] on line 32",
+        ]
+    );
+
+    // Кнопки и панели после ошибки не поглощаются соседними секциями.
+    let config = report.config;
+    assert_eq!(config.phone_panels.len(), 2);
+    assert_eq!(config.phone_panels[0].buttons.len(), 2);
+    assert_eq!(config.phone_panels[0].buttons[0].text, "С-6 ПУ");
+    assert_eq!(config.phone_panels[0].buttons[1].text, "С-9 ПУ");
+    assert_eq!(config.phone_panels[1].buttons[0].internal_address, 338);
+    assert_eq!(config.radio_panels.len(), 1);
+}
+
+#[test]
+fn test_parse_reports_unclosed_section_at_eof() {
+    let broken = INPUT.replace("[#RadioPanels]\n", "");
+    let report = parse_lenient(&broken);
+    assert_eq!(report.diagnostics.len(), 1);
+    assert!(matches!(
+        &report.diagnostics[0].error,
+        ConfigError::UnclosedSection { name, location } if name == "RadioPanels" && location.line == 45
+    ));
+    assert_eq!(report.config.radio_panels[0].buttons[0].slot, 5);
+}
+
+#[test]
+fn test_load_detects_encoding() {
+    let koi8 = KOI8_R.encode(INPUT, EncoderTrap::Strict).unwrap();
+    let cp1251 = WINDOWS_1251.encode(INPUT, EncoderTrap::Strict).unwrap();
+    let mut utf8_bom = vec![0xEF, 0xBB, 0xBF];
+    utf8_bom.extend_from_slice(INPUT.as_bytes());
+
+    let cases = [
+        (koi8, TextEncoding::Koi8R),
+        (cp1251, TextEncoding::Cp1251),
+        (INPUT.as_bytes().to_vec(), TextEncoding::Utf8 { bom: false }),
+        (utf8_bom, TextEncoding::Utf8 { bom: true }),
+    ];
+    for (bytes, expected) in cases {
+        let loaded = load_bytes(&bytes).unwrap();
+        assert_eq!(loaded.encoding, expected);
+        assert_eq!(loaded.text, INPUT);
+        assert_eq!(loaded.config, parse(INPUT).unwrap());
+    }
+
+    // Короткие подписи из заглавных букв тоже распознаются.
+    let label = KOI8_R
+        .encode("text = \"С-6 ПУ\"", EncoderTrap::Strict)
+        .unwrap();
+    assert_eq!(detect_encoding(&label), TextEncoding::Koi8R);
+}
+
+#[test]
+fn test_decode_lossy_reports_offsets() {
+    let decoded = decode_lossy(b"\xEF\xBB\xBFa\xFFb\xD0", TextEncoding::Utf8 { bom: true });
+    assert_eq!(decoded.text, "a\u{FFFD}b\u{FFFD}");
+    assert_eq!(decoded.invalid_offsets, vec![4, 6]);
+}
+
+#[test]
+fn test_encode_config_reports_unrepresentable_characters() {
+    let text = INPUT.replace("\"Диспетчер ПИВП вне ВТ\"", "\"«Диспетчер» — ПИВП\"");
+
+    let err = encode_config(&text, TextEncoding::Koi8R, SubstitutionPolicy::Strict).unwrap_err();
+    let EncodeError::Unrepresentable { issues, .. } = err;
+    let found: Vec<(char, usize, &str)> = issues
+        .iter()
+        .map(|i| (i.ch, i.location.column, i.location.path.as_str()))
+        .collect();
+    let path = "PhonePanels/Panel02/Button01/text";
+    assert_eq!(
+        found,
+        vec![('«', 9, path), ('»', 19, path), ('—', 21, path)]
+    );
+    assert_eq!(issues[0].location.line, 41);
+
+    let encoded =
+        encode_config(&text, TextEncoding::Koi8R, SubstitutionPolicy::Substitute).unwrap();
+    assert_eq!(encoded.substitutions.len(), 3);
+    let saved = decode_lossy(&encoded.bytes, TextEncoding::Koi8R).text;
+    assert_eq!(
+        saved,
+        text.replace("«Диспетчер» — ПИВП", "\"Диспетчер\" - ПИВП")
+    );
+}
+
+#[test]
+fn test_encode_config_locates_characters_in_surrounding_whitespace() {
+    // Неразрывный пробел в KOI8-R есть, а типографского (em space) нет;
+    // trim() отрезает оба.
+    let text = INPUT
+        .replace("name = \"Инженер КСРС\"", "name = \"Инженер КСРС\"\u{2003}")
+        .replace("[PhonePanels]", "\u{2003}\n[PhonePanels]")
+        .trim_end()
+        .to_string()
+        + "\u{2003}";
+
+    let err = encode_config(&text, TextEncoding::Koi8R, SubstitutionPolicy::Strict).unwrap_err();
+    let EncodeError::Unrepresentable { issues, .. } = err;
+    let found: Vec<(usize, usize, &str)> = issues
+        .iter()
+        .map(|i| (i.location.line, i.location.column, i.location.path.as_str()))
+        .collect();
+    let last_line = text.lines().count();
+    assert_eq!(
+        found,
+        vec![(4, 22, "name"), (15, 1, ""), (last_line, 15, ""),]
+    );
+}
+
+#[test]
+fn test_document_tree_of_arbitrary_depth() {
+    let input = "version = 2\n[A]\n[B]\n  [C]\n    depth = 3\n  [#C]\n[#B]\n[#A]\n";
+    let doc = Document::parse(input);
+    assert!(doc.issues.is_empty());
+    assert_eq!(doc.root.entry("version").unwrap().value(), "2");
+
+    let c = doc.root.find("A/B/C").unwrap();
+    assert_eq!(c.location.line, 4);
+    assert_eq!(c.location.column, 4);
+    assert_eq!(c.location.path, "A/B/C");
+
+    let depth = c.entry("depth").unwrap();
+    assert_eq!(depth.location.line, 5);
+    assert_eq!(depth.location.column, 5);
+    assert_eq!(depth.location.path, "A/B/C/depth");
+    assert_eq!(depth.value_location().column, 13);
+
+    assert!(matches!(&doc.root.children[1], Node::Section(a) if a.name == "A"));
+    assert_eq!(doc.to_string(), input);
+}
+
+#[test]
+fn test_document_tree_nests_sections_of_the_same_kind() {
+    let input = "[Group1]\n[Group2]\nk = v\n[#Group2]\n[#Group1]\n";
+    let doc = Document::parse(input);
+    assert!(doc.issues.is_empty(), "{:?}", doc.issues);
+    assert_eq!(
+        doc.root
+            .find("Group1/Group2")
+            .unwrap()
+            .entry("k")
+            .unwrap()
+            .value(),
+        "v"
+    );
+    assert_eq!(doc.to_string(), input);
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Outer {
+        #[serde(rename = "Group")]
+        group: Vec<Inner>,
+    }
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Inner {
+        #[serde(rename = "Group")]
+        group: Vec<Leaf>,
+    }
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Leaf {
+        k: String,
+    }
+    let outer: Outer = bracket::from_str(input).unwrap();
+    assert_eq!(outer.group[0].group[0].k, "v");
+}
+
+#[test]
+fn test_document_tree_reports_structure_issues() {
+    let input = "[A]\n[Item1]\nx = 1\n[Item2]\nx = 2\n[#A]\n[#B]\n";
+    let options = TreeOptions {
+        close_siblings: true,
+        ..Default::default()
+    };
+    let doc = Document::parse_with_options(input, options);
+
+    let a = doc.root.section("A").unwrap();
+    assert_eq!(a.sections().count(), 2);
+    assert_eq!(
+        doc.issues,
+        vec![
+            StructureIssue::Unclosed {
+                name: "Item1".to_string(),
+                location: a.section("Item1").unwrap().location.clone(),
+            },
+            StructureIssue::Mismatched {
+                expected: "Item2".to_string(),
+                found: "A".to_string(),
+                line: 6,
+                location: a.section("Item2").unwrap().location.clone(),
+            },
+            StructureIssue::StrayClosing {
+                name: "B".to_string(),
+                location: match &doc.root.children[1] {
+                    Node::Raw(raw) => raw.location.clone(),
+                    other => panic!("unexpected node {:?}", other),
+                },
+            },
+        ]
+    );
+    assert_eq!(doc.to_string(), input);
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct BracketScreen {
+    internal_address: i32,
+    name: String,
+    #[serde(rename = "PhonePanels")]
+    phone_panels: BracketPanels,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct BracketPanels {
+    #[serde(rename = "Panel")]
+    panels: Vec<BracketPanel>,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct BracketPanel {
+    #[serde(rename = "Button")]
+    buttons: Vec<BracketButton>,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct BracketButton {
+    internal_address: u32,
+    position_x: f32,
+    text: String,
+    #[serde(default)]
+    comment: Option<String>,
+}
+
+#[test]
+fn test_bracket_deserialize_numbered_sections() {
+    let screen: BracketScreen = bracket::from_str(INPUT).unwrap();
+    assert_eq!(screen.internal_address, 331);
+    assert_eq!(screen.name, "Инженер КСРС");
+    assert_eq!(screen.phone_panels.panels.len(), 2);
+    assert_eq!(screen.phone_panels.panels[0].buttons.len(), 2);
+    assert_eq!(screen.phone_panels.panels[0].buttons[1].text, "С-9 ПУ");
+    assert_eq!(
+        screen.phone_panels.panels[1].buttons[0].internal_address,
+        338
+    );
+
+    let broken = INPUT.replace("internal_address = 309", "internal_address = x");
+    let err = bracket::from_str::<BracketScreen>(&broken).unwrap_err();
+    match err {
+        BracketError::Located { location, .. } => {
+            assert_eq!(location.line, 26);
+            assert_eq!(
+                location.path,
+                "PhonePanels/Panel01/Button02/internal_address"
+            );
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[test]
+fn test_bracket_serialize_round_trip() {
+    let screen: BracketScreen = bracket::from_str(INPUT).unwrap();
+    let text = bracket::to_string(&screen).unwrap();
+    assert!(text.contains("[PhonePanels]\n[Panel01]\n[Button01]\ninternal_address = 303\n"));
+    assert!(text.contains("[#Button02]\n[#Panel01]\n[Panel02]\n"));
+    assert!(!text.contains("comment"));
+    assert_eq!(bracket::from_str::<BracketScreen>(&text).unwrap(), screen);
+}
+
+#[test]
+fn test_bracket_round_trip_of_empty_sequence() {
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Top {
+        #[serde(rename = "Panel", default)]
+        panels: Vec<Panel>,
+    }
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Panel {
+        #[serde(rename = "Button", default)]
+        buttons: Vec<BracketButton>,
+    }
+
+    let top = Top {
+        panels: vec![Panel {
+            buttons: Vec::new(),
+        }],
+    };
+    let text = bracket::to_string(&top).unwrap();
+    assert_eq!(text, "[Panel01]\n[#Panel01]\n");
+    assert_eq!(bracket::from_str::<Top>(&text).unwrap(), top);
+
+    let empty = Top { panels: Vec::new() };
+    let text = bracket::to_string(&empty).unwrap();
+    assert_eq!(text, "");
+    assert_eq!(bracket::from_str::<Top>(&text).unwrap(), empty);
+}
+
+#[test]
+fn test_structured_formats_round_trip() {
+    let config = parse(INPUT).unwrap();
+    for format in StructuredFormat::ALL {
+        let text = formats::export(&config, format).unwrap();
+        assert!(text.contains("Инженер КСРС"), "{format}: {text}");
+        assert!(text.contains("Panel01"), "{format}: {text}");
+        assert!(!text.contains("ПД "), "{format}: {text}");
+        assert_eq!(formats::import(&text, format).unwrap(), config, "{format}");
+        assert_eq!(
+            formats::import_to_conf(&text, format).unwrap(),
+            serialize(&config)
+        );
+    }
+    assert_eq!(
+        StructuredFormat::from_path("site/ksrs.yml"),
+        Some(StructuredFormat::Yaml)
+    );
+    assert!(formats::import("{", StructuredFormat::Json).is_err());
+}
+
+#[test]
+fn test_layout_lint_reports_overlaps_and_bounds() {
+    let mut config = parse(INPUT).unwrap();
+    assert!(lint::check_layout(&config).is_empty());
+
+    let panel = &mut config.phone_panels[0];
+    panel.buttons[1].position_x = 0.145; // 0.1 x 0.147 overlap with Button01
+    panel.buttons[0].size_height = -0.1;
+    config.radio_panels[0].buttons[0].position_x = 0.05;
+
+    let issues = lint::check_layout(&config);
+    assert_eq!(issues.len(), 2, "{issues:?}");
+    assert!(matches!(
+        &issues[0],
+        LayoutIssue::NegativeSize { button, .. } if button.button == "Button01"
+    ));
+    assert!(matches!(
+        &issues[1],
+        LayoutIssue::OutOfBounds { button, .. } if button.kind == PanelKind::Radio
+    ));
+
+    config.phone_panels[0].buttons[0].size_height = 0.147;
+    let issues = lint::check_layout(&config);
+    match &issues[0] {
+        LayoutIssue::Overlap {
+            first,
+            second,
+            area,
+        } => {
+            assert_eq!(
+                (first.button.as_str(), second.button.as_str()),
+                ("Button01", "Button02")
+            );
+            assert!((area - 0.1 * 0.147).abs() < 1e-5);
+        }
+        other => panic!("unexpected issue: {other:?}"),
+    }
+}
+
+#[test]
+fn test_radio_slot_cross_reference() {
+    let mut config = parse(INPUT).unwrap();
+    let issues = lint::check_radio_slots(&config);
+    assert!(matches!(
+        &issues[0],
+        RadioSlotIssue::UnbackedSlot { button, slot: 5 } if button.button == "Button01"
+    ));
+    assert_eq!(issues.len(), 3);
+
+    config.available_radiostations[0].slot = 5;
+    config.available_radiostations[1].slot = 5;
+    let issues = lint::check_radio_slots(&config);
+    assert_eq!(
+        issues,
+        vec![RadioSlotIssue::DuplicateSlot {
+            slot: 5,
+            stations: vec!["Улан-Удэ 134.1".to_string(), "Талакан 135.4".to_string()],
+        }]
+    );
+
+    config.available_radiostations[1].slot = 7;
+    let issues = lint::check_radio_slots(&config);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity(), Severity::Warning);
+    assert_eq!(
+        issues[0].to_string(),
+        "radiostation \"Талакан 135.4\" (slot 7) is not placed on any radio panel"
+    );
+}
+
+#[test]
+fn test_phone_address_checks() {
+    let mut config = parse(INPUT).unwrap();
+    assert!(lint::check_phone_addresses(&config).is_empty());
+
+    let buttons = &mut config.phone_panels[0].buttons;
+    buttons[0].internal_address = 331;
+    buttons[1].internal_address = 338;
+    buttons[1].text = " ".to_string();
+    config.phone_panels[1].buttons[0].internal_address = 0;
+
+    let issues = lint::check_phone_addresses(&config);
+    let summary: Vec<_> = issues
+        .iter()
+        .map(|issue| (issue.severity(), issue.to_string()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                Severity::Error,
+                "phone panel ПД 1 Button01 dials the workstation's own address 331".to_string()
+            ),
+            (
+                Severity::Warning,
+                "phone panel ПД 1 Button02 (address 338) has empty text".to_string()
+            ),
+            (
+                Severity::Error,
+                "phone panel ПД 2 Button01 has no internal address".to_string()
+            ),
+        ]
+    );
+
+    config.phone_panels[1].buttons[0].internal_address = 338;
+    let issues = lint::check_phone_addresses(&config);
+    assert!(matches!(
+        issues.last(),
+        Some(AddressIssue::ConflictingLabels { address: 338, buttons }) if buttons.len() == 2
+    ));
+}
+
+struct ShortLabelRule;
+
+impl Rule for ShortLabelRule {
+    fn id(&self) -> &str {
+        "site-short-label"
+    }
+
+    fn description(&self) -> &str {
+        "Phone button labels must fit on one line"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, config: &ScreenConfig) -> Vec<Finding> {
+        config
+            .phone_panels
+            .iter()
+            .flat_map(|panel| panel.buttons.iter())
+            .filter(|button| button.text.chars().count() > 12)
+            .map(|button| Finding {
+                message: format!("{} is too long", button.text),
+                buttons: Vec::new(),
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_linter_rules_severity_and_suppression() {
+    let config = parse(INPUT).unwrap();
+    let ids: Vec<_> = Linter::default()
+        .run(&config)
+        .into_iter()
+        .map(|m| (m.rule, m.severity))
+        .collect();
+    assert_eq!(
+        ids,
+        vec![
+            ("radio-unbacked-slot".to_string(), Severity::Error),
+            ("radio-unplaced-station".to_string(), Severity::Warning),
+            ("radio-unplaced-station".to_string(), Severity::Warning),
+        ]
+    );
+
+    let project = LintConfig::from_toml(
+        r#"
+[rules]
+radio-unplaced-station = "off"
+radio-unbacked-slot = "warn"
+no-such-rule = "error"
+
+[[suppress]]
+rule = "radio-unbacked-slot"
+kind = "radio"
+panel = "Panel02"
+button = "Button01"
+"#,
+    )
+    .unwrap();
+    let mut linter = Linter::new(project.clone());
+    linter.add_rule(Box::new(ShortLabelRule));
+    assert_eq!(linter.unknown_rule_ids(), vec!["no-such-rule"]);
+    let messages = linter.run(&config);
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].severity, Severity::Warning);
+    assert_eq!(
+        messages[1].to_string(),
+        "warning[site-short-label]: Диспетчер ПИВП вне ВТ is too long"
+    );
+
+    let mut project = project;
+    project.suppress[0].panel = "Panel01".to_string();
+    let messages = Linter::new(project).run(&config);
+    assert!(messages.is_empty(), "{messages:?}");
+}
+
+#[test]
+fn test_linter_suppresses_finding_only_when_all_its_buttons_are_suppressed() {
+    let mut config = parse(INPUT).unwrap();
+    config.phone_panels[0].buttons[1].position_x = 0.1;
+    let suppress = |buttons: &[&str]| {
+        let mut toml = String::new();
+        for button in buttons {
+            toml += &format!(
+                "[[suppress]]\nrule = \"layout-overlap\"\npanel = \"Panel01\"\nbutton = \"{}\"\n",
+                button
+            );
+        }
+        let messages = Linter::new(LintConfig::from_toml(&toml).unwrap()).run(&config);
+        messages
+            .iter()
+            .filter(|m| m.rule == "layout-overlap")
+            .count()
+    };
+    assert_eq!(suppress(&[]), 1);
+    assert_eq!(suppress(&["Button01"]), 1);
+    assert_eq!(suppress(&["Button01", "Button02"]), 0);
+}
+
+#[test]
+fn test_linter_matches_individual_builtin_rules() {
+    let mut config = parse(INPUT).unwrap();
+    config.phone_panels[0].buttons[1].position_x = 0.1;
+    config.phone_panels[0].buttons[0].text = String::new();
+    let expected: Vec<_> = crate::rules::builtin_rules()
+        .iter()
+        .flat_map(|rule| {
+            rule.check(&config)
+                .into_iter()
+                .map(|finding| (rule.id().to_string(), finding.message))
+        })
+        .collect();
+    let actual: Vec<_> = Linter::default()
+        .run(&config)
+        .into_iter()
+        .map(|m| (m.rule, m.message))
+        .collect();
+    assert_eq!(actual, expected);
+    assert!(actual.iter().any(|(rule, _)| rule == "layout-overlap"));
+    assert!(actual.iter().any(|(rule, _)| rule == "phone-empty-text"));
+}
+
+#[test]
+fn test_diff_matches_buttons_by_address_and_text() {
+    let old = parse(INPUT).unwrap();
+    let mut new = old.clone();
+    assert!(diff::diff(&old, &new).is_empty());
+
+    // Renumbering alone is not a change.
+    new.phone_panels[0].buttons.swap(0, 1);
+    new.phone_panels[0].buttons[0].id = "Button01".to_string();
+    new.phone_panels[0].buttons[1].id = "Button02".to_string();
+    assert!(diff::diff(&old, &new).is_empty());
+
+    new.phone_panels[0].buttons[0].position_y = 0.2; // С-9 ПУ
+    new.phone_panels[0].buttons[1].text = "С-6".to_string();
+    new.phone_panels[1].buttons.push(PhoneButton {
+        id: "Button02".to_string(),
+        internal_address: 340,
+        text: "Новая".to_string(),
+        ..Default::default()
+    });
+    new.radio_panels.clear();
+    new.available_radiostations[1].slot = 4;
+
+    let changes = diff::diff(&old, &new);
+    assert_eq!(
+        changes.to_string(),
+        "~ radiostation \"Талакан 135.4\": slot -1 -> 4\n\
+         phone panel ПД 1:\n  \
+         ~ Button01 -> Button02 \"С-6\": label \"С-6 ПУ\" -> \"С-6\"\n  \
+         ~ Button02 -> Button01 \"С-9 ПУ\": moved (0.264, 0.016) -> (0.264, 0.200)\n\
+         phone panel ПД 2:\n  \
+         + Button02 \"Новая\" address 340 at (0.000, 0.000) 0.000x0.000\n\
+         - radio panel Panel01 (1 buttons)\n"
+    );
+    let panel = changes.panel(PanelKind::Phone, "ПД 1").unwrap();
+    assert!(panel.buttons[1].moved() && !panel.buttons[1].resized());
+}
+
+#[test]
+fn test_diff_inserted_button_does_not_take_over_a_changed_one() {
+    let old = parse(INPUT).unwrap();
+    let mut new = old.clone();
+    let buttons = &mut new.phone_panels[0].buttons;
+    buttons[0].internal_address = 403;
+    buttons[0].text = "С-6 новый".to_string();
+    buttons[0].position_x = 0.508;
+    buttons.insert(
+        0,
+        PhoneButton {
+            id: "Button03".to_string(),
+            internal_address: 400,
+            text: "Новая".to_string(),
+            position_x: 0.02,
+            position_y: 0.016,
+            size_height: 0.147,
+            size_width: 0.225,
+        },
+    );
+
+    assert_eq!(
+        diff::diff(&old, &new).to_string(),
+        "phone panel ПД 1:\n  \
+         ~ Button01 \"С-6 новый\": moved (0.020, 0.016) -> (0.508, 0.016); \
+         address 303 -> address 403; label \"С-6 ПУ\" -> \"С-6 новый\"\n  \
+         + Button03 \"Новая\" address 400 at (0.020, 0.016) 0.225x0.147\n"
+    );
+}
+
+#[test]
+fn test_diff_does_not_pair_radio_buttons_by_empty_label() {
+    let old = parse(INPUT).unwrap();
+    let mut new = old.clone();
+    new.radio_panels[0].buttons[0] = RadioButton {
+        id: "Button02".to_string(),
+        position_x: 0.5,
+        position_y: 0.5,
+        size_height: 0.158,
+        size_width: 0.4,
+        slot: 3,
+        text: String::new(),
+    };
+
+    let changes = diff::diff(&old, &new);
+    let panel = changes.panel(PanelKind::Radio, "Panel01").unwrap();
+    assert!(
+        matches!(
+            panel.buttons.as_slice(),
+            [ButtonChange::Removed(_), ButtonChange::Added(_)]
+        ),
+        "{changes}"
+    );
+}
+
+#[test]
+fn test_three_way_merge() {
+    let base = parse(INPUT).unwrap();
+
+    let mut ours = base.clone();
+    ours.phone_panels[0].buttons[0].position_x = 0.5;
+    ours.phone_panels[1].buttons[0].text = "Диспетчер ПИВП".to_string();
+    ours.available_radiostations[0].slot = 5;
+
+    let mut theirs = base.clone();
+    theirs.phone_panels[0].buttons[0].size_width = 0.3;
+    theirs.phone_panels[0].buttons.remove(1);
+    theirs.phone_panels[1].buttons.insert(
+        0,
+        PhoneButton {
+            id: "Button01".to_string(),
+            internal_address: 340,
+            text: "Новая".to_string(),
+            ..Default::default()
+        },
+    );
+    theirs.phone_panels[1].buttons[1].id = "Button02".to_string();
+    theirs.name = Some("Инженер".to_string());
+
+    let result = merge::merge(&base, &ours, &theirs);
+    assert!(result.is_clean(), "{:?}", result.conflicts);
+    let merged = result.config;
+    assert_eq!(merged.name.as_deref(), Some("Инженер"));
+    assert_eq!(merged.available_radiostations[0].slot, 5);
+    let p1 = &merged.phone_panels[0].buttons;
+    assert_eq!(p1.len(), 1);
+    assert_eq!((p1[0].position_x, p1[0].size_width), (0.5, 0.3));
+    let p2 = &merged.phone_panels[1].buttons;
+    assert_eq!(p2[0].text, "Диспетчер ПИВП");
+    assert_eq!(
+        (p2[1].id.as_str(), p2[1].internal_address),
+        ("Button02", 340)
+    );
+
+    theirs.phone_panels[0].buttons[0].position_x = 0.7;
+    let result = merge::merge(&base, &ours, &theirs);
+    assert_eq!(
+        result
+            .conflicts
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "phone panel ПД 1 Button01 \"С-6 ПУ\" position: base (0.020, 0.016), \
+             ours (0.500, 0.016), theirs (0.700, 0.016)"
+        ]
+    );
+    assert_eq!(result.config.phone_panels[0].buttons[0].position_x, 0.5);
+}
+
+/// Временный каталог с набором конфигураций для тестов площадки.
+fn fleet_dir(name: &str, files: &[(&str, String)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("megafon-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, text) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let bytes = KOI8_R.encode(text, EncoderTrap::Strict).unwrap();
+        std::fs::write(path, bytes).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn test_fleet_loads_directory_tree() {
+    let other = INPUT
+        .replace("internal_address = 331", "internal_address = 303")
+        .replace("Инженер КСРС", "С-6 ПУ");
+    let dir = fleet_dir(
+        "fleet",
+        &[
+            ("hall/ksrs.conf", INPUT.to_string()),
+            ("hall/c6/ws.CONF", other),
+            ("broken.conf", "internal_address = x\n".to_string()),
+            ("notes.txt", "ignored".to_string()),
+        ],
+    );
+
+    let fleet = Fleet::load(&dir).unwrap();
+    let names: Vec<_> = fleet.workstations.iter().map(|ws| ws.to_string()).collect();
+    assert_eq!(names, vec!["303 С-6 ПУ", "331 Инженер КСРС"]);
+    assert_eq!(fleet.by_name("Инженер КСРС").unwrap().address(), Some(331));
+    assert_eq!(
+        fleet.relative_path(&fleet.by_address(303).unwrap().path),
+        std::path::Path::new("hall/c6/ws.CONF")
+    );
+    assert_eq!(fleet.errors.len(), 1);
+    assert!(fleet.errors[0].path.ends_with("broken.conf"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_call_graph_reachability_and_dot() {
+    let ksrs = parse(INPUT).unwrap();
+    let mut c6 = ksrs.clone();
+    c6.internal_address = Some(303);
+    c6.name = Some("С-6".to_string());
+    c6.phone_panels.truncate(1);
+    c6.phone_panels[0].buttons.truncate(1);
+    c6.phone_panels[0].buttons[0].internal_address = 331;
+    c6.phone_panels[0].buttons[0].text = "КСРС \"инж\"".to_string();
+    let mut isolated = c6.clone();
+    isolated.internal_address = Some(500);
+    isolated.phone_panels.clear();
+
+    let graph = CallGraph::from_configs([&ksrs, &c6, &isolated]);
+    assert_eq!(graph.callees(331).collect::<Vec<_>>(), vec![303, 309, 338]);
+    assert_eq!(graph.callers(331), vec![303]);
+    assert_eq!(graph.path(303, 338), Some(vec![303, 331, 338]));
+    assert!(graph.can_reach(303, 309));
+    assert_eq!(graph.cannot_reach(338), vec![500]);
+    assert_eq!(
+        graph.subscriber(338).unwrap().name.as_deref(),
+        Some("Диспетчер ПИВП вне ВТ")
+    );
+
+    let dot = graph.to_dot();
+    assert!(dot.contains("    \"303\" [label=\"303\\nС-6\", shape=box];\n"));
+    assert!(dot.contains("    \"309\" [label=\"309\\nС-9 ПУ\", shape=ellipse];\n"));
+    assert!(dot.contains("    \"303\" -> \"331\" [label=\"КСРС \\\"инж\\\"\"];\n"));
+}
+
+#[test]
+fn test_fleet_dangling_report() {
+    let c6 = INPUT
+        .replace("internal_address = 331", "internal_address = 303")
+        .replace("Инженер КСРС", "С-6")
+        .replace(
+            "internal_address = 303\nposition_x",
+            "internal_address = 331\nposition_x",
+        );
+    let dir = fleet_dir(
+        "dangling",
+        &[("ksrs.conf", INPUT.to_string()), ("c6.conf", c6)],
+    );
+    let fleet = Fleet::load(&dir).unwrap();
+
+    let report = fleet.dangling_report(&[338]);
+    let lines: Vec<String> = report.buttons.iter().map(|b| b.to_string()).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "303 С-6: phone panel ПД 1 Button02 \"С-9 ПУ\" dials unknown address 309",
+            "331 Инженер КСРС: phone panel ПД 1 Button02 \"С-9 ПУ\" dials unknown address 309",
+        ]
+    );
+    assert!(report.uncallable.is_empty());
+
+    let report = fleet.dangling_report(&[309, 338]);
+    assert!(report.is_empty(), "{report}");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_directory_flags_mismatched_labels() {
+    let csv = "address;name;position;department\n\
+               303;С-6 ПУ;Старший диспетчер;Служба движения\n\
+               309;С-9 ПУ;Диспетчер;Служба движения\n\
+               338;Диспетчер ПИВП вне ВТ;;ПИВП\n";
+    let directory = Directory::from_csv(csv).unwrap();
+    assert_eq!(directory.addresses(), vec![303, 309, 338]);
+    assert_eq!(
+        directory.get(303).unwrap().to_string(),
+        "303 С-6 ПУ (Старший диспетчер, Служба движения)"
+    );
+
+    let cfg = parse(INPUT).unwrap();
+    assert!(directory.check_labels(&cfg).is_empty());
+
+    let swapped =
+        parse(&INPUT.replacen("internal_address = 303", "internal_address = 309", 1)).unwrap();
+    let lines: Vec<String> = directory
+        .check_labels(&swapped)
+        .iter()
+        .map(|m| m.to_string())
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            "phone panel ПД 1 Button01 \"С-6 ПУ\" dials 309 С-9 ПУ (Диспетчер, Служба движения), \
+             but the label matches 303 С-6 ПУ (Старший диспетчер, Служба движения)"
+        ]
+    );
+
+    let duplicate = "address,name\n303,С-6\n303,С-9\n";
+    assert!(matches!(
+        Directory::from_csv(duplicate),
+        Err(DirectoryError::DuplicateAddress {
+            address: 303,
+            line: 3
+        })
+    ));
+}
+
+#[test]
+fn test_fleet_replace_address_saves_only_touched_files() {
+    let c9 = INPUT
+        .replace("internal_address = 331", "internal_address = 309")
+        .replace("internal_address = 303", "internal_address = 338");
+    let dir = fleet_dir(
+        "replace",
+        &[("ksrs.conf", INPUT.to_string()), ("c9.conf", c9)],
+    );
+    let c9_before = std::fs::read(dir.join("c9.conf")).unwrap();
+    let fleet = Fleet::load(&dir).unwrap();
+
+    let edit = fleet.replace_address(303, 403, Some("С-6 ПУ (новый)"));
+    assert_eq!(edit.files.len(), 1);
+    let file = &edit.files[0];
+    assert_eq!(file.workstation.address(), Some(331));
+    assert_eq!(
+        file.buttons[0].to_string(),
+        "phone panel ПД 1 Button01: 303 \"С-6 ПУ\" -> 403 \"С-6 ПУ (новый)\""
+    );
+    let lines: Vec<(Option<String>, Option<String>)> = file
+        .changed_lines()
+        .into_iter()
+        .map(|change| (change.old, change.new))
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            (
+                Some("internal_address = 303".to_string()),
+                Some("internal_address = 403".to_string())
+            ),
+            (
+                Some("text = \"С-6 ПУ\"".to_string()),
+                Some("text = \"С-6 ПУ (новый)\"".to_string())
+            ),
+        ]
+    );
+
+    edit.save().unwrap();
+    assert_eq!(std::fs::read(dir.join("c9.conf")).unwrap(), c9_before);
+    let saved = KOI8_R.encode(&file.text, EncoderTrap::Strict).unwrap();
+    assert_eq!(std::fs::read(dir.join("ksrs.conf")).unwrap(), saved);
+    assert!(fleet.replace_address(999, 1, None).is_empty());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_fleet_replace_address_is_byte_identical_on_phone_only_config() {
+    let dir = fleet_dir(
+        "replace-phone-only",
+        &[("ksrs.conf", PHONE_ONLY.to_string())],
+    );
+    let fleet = Fleet::load(&dir).unwrap();
+    fleet.replace_address(303, 403, None).save().unwrap();
+
+    let expected = PHONE_ONLY.replace("internal_address = 303", "internal_address = 403");
+    assert_eq!(
+        std::fs::read(dir.join("ksrs.conf")).unwrap(),
+        KOI8_R.encode(&expected, EncoderTrap::Strict).unwrap()
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_fleet_label_report_and_canonical_label() {
+    let c9 = INPUT
+        .replace("internal_address = 331", "internal_address = 309")
+        .replace("Инженер КСРС", "С-9 ПУ")
+        .replace("text = \"С-6 ПУ\"", "text = \"С6 ПУ\"");
+    let c12 = INPUT
+        .replace("internal_address = 331", "internal_address = 312")
+        .replace("Инженер КСРС", "С-12 ПУ")
+        .replace("text = \"С-6 ПУ\"", "text = \"Сектор 6\"");
+    let dir = fleet_dir(
+        "labels",
+        &[
+            ("ksrs.conf", INPUT.to_string()),
+            ("c9.conf", c9.clone()),
+            ("c12.conf", c12),
+            (
+                "c9-copy.conf",
+                c9.replace("internal_address = 309", "internal_address = 319"),
+            ),
+        ],
+    );
+    let fleet = Fleet::load(&dir).unwrap();
+    let report = fleet.label_report();
+    assert!(report.get(338).unwrap().is_consistent());
+    assert_eq!(report.inconsistent().count(), 1);
+    assert_eq!(
+        report.to_string(),
+        "303:\n\
+         \x20 [1] \"С6 ПУ\" - 309 С-9 ПУ, 319 С-9 ПУ\n\
+         \x20 [2] \"Сектор 6\" - 312 С-12 ПУ\n\
+         \x20 [3] \"С-6 ПУ\" - 331 Инженер КСРС\n"
+    );
+
+    let edit = fleet.apply_label(303, "С-6 ПУ");
+    assert_eq!(edit.files.len(), 3);
+    assert_eq!(edit.button_count(), 3);
+    edit.save().unwrap();
+    let fleet = Fleet::load(&dir).unwrap();
+    assert_eq!(fleet.label_report().inconsistent().count(), 0);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_fleet_apply_label_is_byte_identical_on_phone_only_config() {
+    let dir = fleet_dir("label-phone-only", &[("ksrs.conf", PHONE_ONLY.to_string())]);
+    let fleet = Fleet::load(&dir).unwrap();
+    fleet.apply_label(303, "Сектор 6").save().unwrap();
+
+    let expected = PHONE_ONLY.replace("text = \"С-6 ПУ\"", "text = \"Сектор 6\"");
+    assert_eq!(
+        std::fs::read(dir.join("ksrs.conf")).unwrap(),
+        KOI8_R.encode(&expected, EncoderTrap::Strict).unwrap()
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
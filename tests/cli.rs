@@ -0,0 +1,245 @@
+use encoding::all::KOI8_R;
+use encoding::{EncoderTrap, Encoding};
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+const CONFIG: &str = "internal_address = 331
+name = \"Инженер КСРС\"
+[PhonePanels]
+[Panel01]
+[Button01]
+internal_address = 303
+position_x = 0.02
+position_y = 0.016
+size_height = 0.147
+size_width = 0.225
+text = \"С-6 ПУ\"
+[#Button01]
+[Button02]
+internal_address = 309
+position_x = 0.264
+position_y = 0.016
+size_height = 0.147
+size_width = 0.225
+text = \"С-9 ПУ\"
+[#Button02]
+[#Panel01]
+[#PhonePanels]
+";
+
+/// Временный каталог теста; удаляется при завершении.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("megafon-cli-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    /// Записывает конфигурацию в KOI8-R, как её сохраняет пульт.
+    fn write(&self, name: &str, text: &str) -> PathBuf {
+        let path = self.0.join(name);
+        std::fs::write(&path, KOI8_R.encode(text, EncoderTrap::Strict).unwrap()).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn run(args: &[&Path]) -> Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_megafon-cfg"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+// -----------------------------------------------------------------------------
+// diff
+// -----------------------------------------------------------------------------
+
+#[test]
+fn test_diff_of_identical_configs_is_empty() {
+    let dir = TempDir::new("diff-same");
+    let old = dir.write("old.conf", CONFIG);
+    let new = dir.write("new.conf", CONFIG);
+
+    let output = run(&[Path::new("diff"), &old, &new]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stdout(&output).is_empty());
+}
+
+#[test]
+fn test_diff_prints_changes_and_fails() {
+    let dir = TempDir::new("diff-changed");
+    let old = dir.write("old.conf", CONFIG);
+    let new = dir.write("new.conf", &CONFIG.replace("= 309", "= 409"));
+
+    let output = run(&[Path::new("diff"), &old, &new]);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "phone panel ПД 1:\n  ~ Button02 \"С-9 ПУ\": address 309 -> address 409\n"
+    );
+}
+
+#[test]
+fn test_diff_of_unreadable_file_is_an_error() {
+    let dir = TempDir::new("diff-missing");
+    let old = dir.write("old.conf", CONFIG);
+    let missing = dir.0.join("missing.conf");
+
+    let output = run(&[Path::new("diff"), &old, &missing]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).is_empty());
+    assert!(stderr(&output).starts_with(&format!("error: {}: ", missing.display())));
+}
+
+// -----------------------------------------------------------------------------
+// merge
+// -----------------------------------------------------------------------------
+
+#[test]
+fn test_merge_driver_writes_clean_merge_over_ours() {
+    let dir = TempDir::new("merge-clean");
+    let base = dir.write("base.conf", CONFIG);
+    let ours = dir.write("ours.conf", &CONFIG.replace("С-6 ПУ", "С-6"));
+    let theirs = dir.write("theirs.conf", &CONFIG.replace("= 309", "= 409"));
+
+    let output = run(&[Path::new("merge"), &base, &ours, &theirs]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    let expected = CONFIG.replace("С-6 ПУ", "С-6").replace("= 309", "= 409");
+    assert_eq!(
+        std::fs::read(&ours).unwrap(),
+        KOI8_R.encode(&expected, EncoderTrap::Strict).unwrap()
+    );
+}
+
+#[test]
+fn test_merge_driver_leaves_ours_untouched_on_conflict() {
+    let dir = TempDir::new("merge-conflict");
+    let base = dir.write("base.conf", CONFIG);
+    let ours = dir.write("ours.conf", &CONFIG.replace("С-6 ПУ", "С-6"));
+    let theirs = dir.write("theirs.conf", &CONFIG.replace("С-6 ПУ", "Сектор 6"));
+    let before = std::fs::read(&ours).unwrap();
+
+    let output = run(&[Path::new("merge"), &base, &ours, &theirs]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(std::fs::read(&ours).unwrap(), before);
+    assert_eq!(
+        stderr(&output),
+        "conflict: phone panel ПД 1 Button01 \"С-6 ПУ\" text: \
+         base \"С-6 ПУ\", ours \"С-6\", theirs \"Сектор 6\"\n"
+    );
+    assert!(stdout(&output).is_empty());
+}
+
+// -----------------------------------------------------------------------------
+// replace-address
+// -----------------------------------------------------------------------------
+
+#[test]
+fn test_replace_address_dry_run_previews_relative_paths() {
+    let dir = TempDir::new("replace-dry-run");
+    std::fs::create_dir(dir.0.join("site")).unwrap();
+    let config = dir.write("site/a.conf", CONFIG);
+    let before = std::fs::read(&config).unwrap();
+
+    let output = run(&[
+        Path::new("replace-address"),
+        &dir.0,
+        Path::new("303"),
+        Path::new("403"),
+        Path::new("--dry-run"),
+    ]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    let expected = format!(
+        "{} (331 Инженер КСРС)\n  \
+         phone panel ПД 1 Button01: 303 \"С-6 ПУ\" -> 403 \"С-6 ПУ\"\n      \
+         6 - internal_address = 303\n      \
+         6 + internal_address = 403\n",
+        Path::new("site").join("a.conf").display()
+    );
+    assert_eq!(stdout(&output), expected);
+    assert_eq!(std::fs::read(&config).unwrap(), before);
+}
+
+// -----------------------------------------------------------------------------
+// info
+// -----------------------------------------------------------------------------
+
+#[test]
+fn test_info_reports_every_readable_file() {
+    let dir = TempDir::new("info");
+    let config = dir.write("a.conf", CONFIG);
+    let missing = dir.0.join("missing.conf");
+
+    let output = run(&[Path::new("info"), &missing, &config]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with(&format!("error: {}: ", missing.display())));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "{}\n  encoding:         KOI8-R\n  name:             Инженер КСРС\n  \
+             internal address: 331\n  radiostations:    0\n  phone panels:     1\n  \
+             phone buttons:    2\n  radio panels:     0\n  radio buttons:    0\n",
+            config.display()
+        )
+    );
+}
+
+#[test]
+fn test_info_requires_files() {
+    let output = run(&[Path::new("info")]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).is_empty());
+    assert!(
+        stderr(&output).contains("<FILES>..."),
+        "{}",
+        stderr(&output)
+    );
+}
+
+// -----------------------------------------------------------------------------
+// validate
+// -----------------------------------------------------------------------------
+
+#[test]
+fn test_validate_passes_clean_config() {
+    let dir = TempDir::new("validate-clean");
+    let config = dir.write("a.conf", CONFIG);
+
+    let output = run(&[Path::new("validate"), &config]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stderr(&output).is_empty());
+}
+
+#[test]
+fn test_validate_fails_on_finding() {
+    let dir = TempDir::new("validate-finding");
+    let config = dir.write("a.conf", &CONFIG.replace("= 309", "= 331"));
+
+    let output = run(&[Path::new("validate"), &config]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "{}: error[phone-self-call]: phone panel ПД 1 Button02 \
+             dials the workstation's own address 331\n",
+            config.display()
+        )
+    );
+}